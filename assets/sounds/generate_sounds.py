@@ -0,0 +1,68 @@
+"""Generates the built-in sounds of the Locking Pomodoro Timer.
+
+The sounds are synthesized from sine and square waves, so they contain no recorded or third-party
+material. Run this script from the root of the repository to recreate the WAV files in
+assets/sounds:
+
+    python3 assets/sounds/generate_sounds.py
+"""
+import math
+import struct
+import wave
+
+RATE = 22050
+
+
+def write(name, samples):
+    """Writes the samples normalized to 80 % of the full scale as 16 bit mono WAV file."""
+    peak = max(abs(x) for x in samples) or 1
+    with wave.open(f'assets/sounds/{name}.wav', 'wb') as w:
+        w.setnchannels(1)
+        w.setsampwidth(2)
+        w.setframerate(RATE)
+        w.writeframes(b''.join(struct.pack('<h', int(x / peak * 0.8 * 32767)) for x in samples))
+
+
+def tone(freqs, dur, decay, attack=0.005, wave_fn=math.sin):
+    """Synthesizes the partials (frequency, amplitude) with a linear attack and exponential decay."""
+    out = []
+    for i in range(int(dur * RATE)):
+        t = i / RATE
+        env = min(1, t / attack) * math.exp(-decay * t)
+        out.append(env * sum(a * wave_fn(2 * math.pi * f * t) for f, a in freqs))
+    return out
+
+
+def silence(dur):
+    return [0.0] * int(dur * RATE)
+
+
+# bell: inharmonic partials, long decay
+bell = tone([(880, 1), (880 * 2.0, 0.5), (880 * 2.76, 0.35), (880 * 5.4, 0.15)], 2.5, 2.2)
+write('bell', bell)
+
+# chime: ascending three notes
+chime = []
+for i, f in enumerate([659.25, 783.99, 1046.5]):
+    chime += tone([(f, 1), (f * 2, 0.3)], 1.2 if i == 2 else 0.35, 4 if i < 2 else 2.5)
+write('chime', chime)
+
+# alarm: repeated two-tone beeps
+alarm = []
+for _ in range(4):
+    alarm += tone([(988, 1), (988 * 3, 0.2)], 0.15, 3) + silence(0.05)
+    alarm += tone([(1319, 1), (1319 * 3, 0.2)], 0.15, 3) + silence(0.25)
+write('alarm', alarm)
+
+
+# digital: square-ish beeps like a kitchen timer
+def square(x):
+    return math.copysign(1, math.sin(x)) * 0.6 + 0.4 * math.sin(x)
+
+
+digital = []
+for _ in range(3):
+    for _ in range(4):
+        digital += tone([(2000, 1)], 0.06, 0, attack=0.002, wave_fn=square) + silence(0.06)
+    digital += silence(0.35)
+write('digital', digital)
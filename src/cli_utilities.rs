@@ -1,258 +1,913 @@
-use crate::end_events::start_end_event;
-use crate::input_handler;
-use crate::message_creator::{
-    generate_print_message_before_additional_break, generate_print_message_before_break,
-    generate_print_message_before_pomodoro,
+//! This module contains the functions to run the Pomodoro timer on the terminal.
+use crate::clock::Clock;
+use crate::end_events::{
+    start_end_event, start_end_event_in_background, EndEvent, EndEventContext,
 };
-use crate::pomo_info::PomoInfo;
+use crate::history::{get_filepath_history, HistoryRecord};
+use crate::http_api::start_http_server;
+use crate::input_handler;
+use crate::key_bindings::{describe_minutes, Action, KeyBindings};
+use crate::persistence::{get_filepath_state, read_state, remove_state};
 use crate::pomodoro_options::PomodoroOptions;
+use crate::session::{Phase, PomodoroSession, SessionOutcome};
+use crate::simulation::{EndEventRecorder, EventTrigger};
 use crate::timer::Timer;
-use indicatif::{ProgressBar, ProgressStyle};
+use crate::tui::TuiView;
+use crate::view::View;
+use chrono::{DateTime, Local};
 use log::debug;
 use std::ops::ControlFlow;
-use std::thread;
-use std::time::{Duration, Instant};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Starts the Pomodoro timer.
 ///
-/// The function reads the Pomodoro options from the JSON file and starts the Pomodoro timer.
+/// The function prints the Pomodoro options and runs a `PomodoroSession` on the terminal.
 /// The timer runs in a loop and can be repeated by pressing the enter key.
 /// The timer can be paused and resumed by pressing the 'p' and 'r' keys respectively.
 /// It can be stopped by pressing the 'q' key.
-/// Also it can be exited by pressing the 'ctrl+c' key combination.
+/// Also it can be quit by pressing the 'ctrl+c' key combination. The function returns then as
+/// well, restoring the terminal and exiting the program is left to the caller.
+/// On Unix, the timer can also be controlled through the control socket, see `control`.
+/// If the option `http_port` is set, it can also be controlled through the HTTP API, see `http_api`.
+/// On Linux, the timer is also published on the session bus, see `dbus_service`.
+/// The state of the running session is saved, so an interrupted session can be resumed on the
+/// next start, see `persistence`.
+/// If the option `tui` is set, the session is shown as a full-screen interface, see `tui`.
 ///
 /// # Arguments
 /// * `options` - The Pomodoro options.
-pub(crate) fn start_pomodoro(options: &PomodoroOptions) {
+/// * `label` - The label of the task to start with, if any.
+///
+/// # Returns
+/// How the session has ended.
+pub fn start_pomodoro(options: PomodoroOptions, label: Option<String>) -> SessionOutcome {
     // Use the imported data
     println!(
         "Options: {}",
-        serde_json::to_string_pretty(options).unwrap()
+        serde_json::to_string_pretty(&options).unwrap()
     );
 
-    // Convert the duration and additional duration to `Duration` type
-    let duration: Duration = Duration::from_secs((options.duration_pomodoro * 60) as u64);
-    let additional_duration: Duration =
-        Duration::from_secs((options.additional_duration * 60) as u64);
-
-    let mut counter = 0;
-    let mut input = String::new();
-    let end_event = || {
-        start_end_event(&options.end_event_pomodoro);
-    };
     debug!("Starting input stream.");
-    let receiver = input_handler::start_input_stream();
-    loop {
-        // Check if the timer should be repeated
-        if counter != 0 && !options.auto_start_pomodoro {
-            input.clear();
-            input = ask_for_new_pomodoro(&receiver, &options);
-        } else {
-            input = "".to_string();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    input_handler::spawn_input_thread(sender.clone());
+    let mut session = PomodoroSession::new(options);
+    session.set_label(label);
+    // The full-screen interface is started first, so it shows the following messages and errors
+    // instead of being drawn over them.
+    if session.options().tui {
+        start_tui_view(&mut session);
+    }
+    let view = session.view();
+    match get_filepath_state(&session.options().filepath_state) {
+        Ok(filepath_state) => {
+            if ask_for_resume(&mut session, &filepath_state, &receiver).is_break() {
+                view.close();
+                return SessionOutcome::Quit;
+            }
+            session.save_state_to(filepath_state);
         }
-
-        if input.trim().is_empty() {
-            let pomo_info = PomoInfo::from_options(options, counter);
-
-            let print_message = generate_print_message_before_pomodoro(&pomo_info, &options);
-            println!("{}", print_message);
-
-            execute_timer(duration, &receiver, end_event);
-
-            if options.additional_duration != 0 {
-                let print_message =
-                    generate_print_message_before_additional_break(&pomo_info, &options);
-                println!("{}", print_message);
-                time_with_progress_bar(additional_duration, &receiver, || {
-                    start_end_event(&options.end_event_additional_pomodoro)
-                });
+        Err(e) => log::error!("Failed to get the path of the state file: {:#}", e),
+    }
+    #[cfg(unix)]
+    {
+        let filepath_socket = crate::control::get_filepath_socket();
+        if let Err(e) = crate::control::start_control_server(
+            &filepath_socket,
+            sender.clone(),
+            session.status_handle(),
+        ) {
+            log::error!("Failed to start the control server: {:#}", e);
+            view.show_message(&format!("Error: {:#}", e));
+        }
+    }
+    #[cfg(target_os = "linux")]
+    match crate::dbus_service::DbusService::start_on_session_bus(
+        sender.clone(),
+        session.status_handle(),
+    ) {
+        Ok(service) => session.on_phase_event(move |event| {
+            if let Err(e) = service.notify(event) {
+                log::error!("{:#}", e);
             }
+        }),
+        // Without a session bus, e.g. on a server, the timer works as before.
+        Err(e) => log::warn!("Failed to start the D-Bus service: {:#}", e),
+    }
+    if let Some(port) = session.options().http_port {
+        let result = get_filepath_history(&session.options().filepath_history).and_then(
+            |filepath_history| {
+                start_http_server(port, sender, session.status_handle(), filepath_history)
+            },
+        );
+        match result {
+            Ok(address) => view.show_message(&format!("HTTP API listening on http://{}", address)),
+            Err(e) => {
+                log::error!("Failed to start the HTTP API: {:#}", e);
+                view.show_message(&format!("Error: {:#}", e));
+            }
+        }
+    }
+    let outcome = session.run(&receiver);
+    view.close();
+    outcome
+}
 
-            if !pomo_info.break_duration.is_zero() {
-                if !options.auto_start_break {
-                    if pomo_info.is_long_break_coming {
-                        println!(
-                            "Press enter to start the long break of {:.0} minutes.",
-                            pomo_info.break_duration.as_secs() / 60
-                        );
-                    } else {
-                        println!(
-                            "Press enter to start the short break of {:.0} minutes.",
-                            pomo_info.break_duration.as_secs() / 60
-                        );
-                    }
-                    loop {
-                        let pressed_key = receiver.recv().expect("Failed to receive input.");
-                        if pressed_key == "\n" {
-                            input = "".to_string();
-                            break;
-                        }
-                    }
-                }
-                let print_message = generate_print_message_before_break(&pomo_info, &options);
-                println!("{}", print_message);
-                execute_timer(pomo_info.break_duration, &receiver, end_event);
+/// Shows the session as a full-screen interface.
+///
+/// If the terminal does not support the interface, the error is shown and the session is shown
+/// line by line as usual. The view of the session has to be closed when the session has ended.
+fn start_tui_view(session: &mut PomodoroSession) {
+    match TuiView::start(session.options()) {
+        Ok(tui_view) => {
+            let tui_view = Arc::new(tui_view);
+            let phase_view = tui_view.clone();
+            session.on_phase_event(move |event| phase_view.on_phase_event(event));
+            session.set_view(tui_view);
+        }
+        Err(e) => {
+            log::error!("Failed to start the full-screen interface: {:#}", e);
+            session.view().show_message(&format!("Error: {:#}", e));
+        }
+    }
+}
+
+/// Asks the user whether to resume the interrupted session saved in the state file.
+///
+/// If the user declines, the state file is removed. A state file which can not be read is
+/// reported and ignored. The question is shown by the view of the session.
+///
+/// # Arguments
+/// * `session` - The session to resume.
+/// * `filepath_state` - The path to the state file.
+/// * `receiver` - The receiver for input events.
+///
+/// # Returns
+/// A control flow which breaks when the user quit the program.
+fn ask_for_resume(
+    session: &mut PomodoroSession,
+    filepath_state: &std::path::Path,
+    receiver: &std::sync::mpsc::Receiver<String>,
+) -> ControlFlow<()> {
+    let view = session.view();
+    let state = match read_state(filepath_state) {
+        Ok(Some(state)) => state,
+        Ok(None) => return ControlFlow::Continue(()),
+        Err(e) => {
+            log::error!("{:#}", e);
+            view.show_message(&format!("Error: {:#}", e));
+            return ControlFlow::Continue(());
+        }
+    };
+    let key_bindings = session.options().key_bindings.clone();
+    view.show_message(&format!(
+        "Resume the interrupted {}? ({})",
+        state.describe(session.options()),
+        help_resume(&key_bindings)
+    ));
+    for input in receiver.iter() {
+        match key_bindings.action(&input) {
+            Some(Action::Confirm) => {
+                session.resume(state);
+                return ControlFlow::Continue(());
             }
-        } else {
-            break;
+            Some(Action::QuitPhase) => break,
+            // The state file is kept, so the session can still be resumed on the next start.
+            Some(Action::QuitApp) => return ControlFlow::Break(()),
+            _ => debug!("Invalid input: {}", input),
         }
-        counter += 1;
     }
+    if let Err(e) = remove_state(filepath_state) {
+        log::error!("{:#}", e);
+    }
+    ControlFlow::Continue(())
 }
 /// Asks the user if they want to repeat the Pomodoro timer.
 ///
-/// This function prompts the user to press enter to repeat the timer or 'q' to quit.
+/// This function prompts the user to press the confirm key to repeat the timer or the quit key
+/// to quit, see `KeyBindings`.
 /// It also provides interval reminders to get back to work if the user does not respond
-/// within a specified time.
+/// within a specified time. It blocks until the next input or reminder, so it uses no CPU while
+/// waiting. If no input can be received anymore, it quits.
 ///
 /// # Arguments
 /// * `receiver` - The receiver for input events.
 /// * `options` - The Pomodoro options.
+/// * `context` - The finished phase, used to fill the templates of the reminder.
+/// * `clock` - The clock measuring the interval of the reminder.
+/// * `recorder` - The recorder of the end events, if they are recorded instead of started.
+/// * `view` - The view showing the question and the reminders.
 ///
 /// # Returns
-/// A string indicating the user's choice.
-fn ask_for_new_pomodoro(
+/// A string indicating the user's choice or the outcome of the session if the user quit the
+/// program.
+pub(crate) fn ask_for_new_pomodoro(
     receiver: &std::sync::mpsc::Receiver<String>,
     options: &PomodoroOptions,
-) -> String {
+    context: &EndEventContext,
+    clock: &dyn Clock,
+    recorder: Option<&EndEventRecorder>,
+    view: &Arc<dyn View>,
+) -> ControlFlow<SessionOutcome, String> {
     let input;
-    println!("Do you want to repeat the timer? (Press enter to repeat and 'q' to quit.)");
-    let mut start_time = Instant::now();
+    let key_bindings = &options.key_bindings;
+    view.show_message(&format!(
+        "Do you want to repeat the timer? (Press {}.)",
+        key_bindings.help(&[(Action::Confirm, "repeat"), (Action::QuitPhase, "quit")])
+    ));
+    let reminder_interval = Duration::from_secs(options.interval_reminder_after_break as u64 * 60);
+    let mut next_reminder = clock.now() + reminder_interval;
     loop {
-        let pressed_key = receiver.try_recv();
+        let pressed_key = if reminder_interval.is_zero() {
+            receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            clock.recv_timeout(
+                receiver,
+                next_reminder.saturating_duration_since(clock.now()),
+            )
+        };
         match pressed_key {
-            Ok(pressed_key) => {
-                if pressed_key == "q" {
+            Ok(pressed_key) => match key_bindings.action(&pressed_key) {
+                Some(Action::QuitPhase) => {
                     input = "q".to_string();
                     break;
-                } else if pressed_key == "\n" {
+                }
+                Some(Action::Confirm) => {
                     input = "".to_string();
                     break;
                 }
-            }
-            Err(_) => {
-                let reminder_is_active = options.interval_reminder_after_break != 0;
-                if reminder_is_active {
-                    let elapsed_time = start_time.elapsed().as_secs();
-                    if elapsed_time >= options.interval_reminder_after_break as u64 * 60 {
-                        println!("Get back to work!");
-                        start_end_event(&options.event_reminder_after_break);
-                        start_time = Instant::now();
-                    }
+                Some(Action::QuitApp) => return ControlFlow::Break(SessionOutcome::Quit),
+                _ => debug!("Invalid input: {}", pressed_key),
+            },
+            Err(RecvTimeoutError::Timeout) => {
+                view.show_message("Get back to work!");
+                let reminder = &options.event_reminder_after_break;
+                match recorder {
+                    Some(recorder) => recorder.record(EventTrigger::Reminder, reminder, context),
+                    None => start_end_event(reminder, options, context, view),
                 }
+                next_reminder = clock.now() + reminder_interval;
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                debug!("No input can be received anymore.");
+                input = "q".to_string();
+                break;
             }
         }
     }
-    input
+    ControlFlow::Continue(input)
 }
 
-/// Executes the timer with the specified duration.
+/// Asks the user for the label of the task they are working on before a Pomodoro starts.
 ///
-/// This function runs the timer for the given duration and executes the end event when the timer ends.
+/// The recent labels are offered and can be picked by typing their number. Pressing enter without
+/// typing a label keeps the current label and typing '-' removes it. The typed text is not
+/// affected by the key bindings, only characters are added to it. While a phase is running, the
+/// label is typed in the loop of `time_with_progress_bar` instead, so the timer keeps running.
 ///
 /// # Arguments
-/// * `duration` - The duration of the timer.
 /// * `receiver` - The receiver for input events.
-/// * `end_event` - The function to execute when the timer ends.
-fn execute_timer<F: Fn()>(
-    duration: Duration,
+/// * `current_label` - The label of the current task, if any.
+/// * `recent_labels` - The recently used labels, starting with the latest one.
+/// * `view` - The view showing the question and the typed label.
+///
+/// # Returns
+/// The selected label or `None` if no label is selected. The outcome of the session is returned
+/// instead if the user quit the program or no input can be received anymore.
+pub(crate) fn ask_for_label(
     receiver: &std::sync::mpsc::Receiver<String>,
-    end_event: F,
-) {
-    time_with_progress_bar(duration, &receiver, end_event);
-    println!("Times up!");
+    current_label: Option<&str>,
+    recent_labels: &[String],
+    view: &dyn View,
+) -> ControlFlow<SessionOutcome, Option<String>> {
+    show_label_question(current_label, recent_labels, view);
+    let mut input = String::new();
+    loop {
+        let Ok(pressed_key) = receiver.recv() else {
+            debug!("No input can be received anymore.");
+            return ControlFlow::Break(SessionOutcome::Ended);
+        };
+        if pressed_key == "ctrl+c" {
+            view.show_input(None);
+            return ControlFlow::Break(SessionOutcome::Quit);
+        }
+        if type_label(&mut input, &pressed_key).is_break() {
+            view.show_input(None);
+            break;
+        }
+        view.show_input(Some(&input));
+    }
+    ControlFlow::Continue(select_label(&input, current_label, recent_labels))
 }
 
-/// Executes the timer with the specified duration and displays a progress bar.
+/// Shows the question for the task label and the recent labels to pick from.
 ///
-/// The timer runs in a separate thread and increments the progress bar every second.
-/// It can be paused and resumed using the 'p' and 'r' keys respectively.
-/// Also it can be stopped using the 'q' key.
+/// # Arguments
+/// * `current_label` - The label of the current task, if any.
+/// * `recent_labels` - The recently used labels, starting with the latest one.
+/// * `view` - The view showing the question.
+fn show_label_question(current_label: Option<&str>, recent_labels: &[String], view: &dyn View) {
+    match current_label {
+        Some(label) => view.show_message(&format!(
+            "What are you working on? (Press enter to keep '{}' and type '-' to remove the label.)",
+            label
+        )),
+        None => view.show_message("What are you working on? (Press enter to skip.)"),
+    }
+    if !recent_labels.is_empty() {
+        let choices: Vec<String> = recent_labels
+            .iter()
+            .enumerate()
+            .map(|(index, label)| format!("{}) {}", index + 1, label))
+            .collect();
+        view.show_message(&format!("Recent: {}", choices.join("  ")));
+    }
+}
+
+/// Adds the pressed key to the typed label.
 ///
 /// # Arguments
-/// * `duration` - The duration of the timer.
+/// * `input` - The label typed so far.
+/// * `pressed_key` - The pressed key, which adds a character or removes the last one.
+///
+/// # Returns
+/// A control flow which breaks when enter is pressed, so the label is complete.
+fn type_label(input: &mut String, pressed_key: &str) -> ControlFlow<()> {
+    if pressed_key == "\n" {
+        return ControlFlow::Break(());
+    } else if pressed_key == "backspace" {
+        input.pop();
+    } else if pressed_key.chars().count() == 1 {
+        input.push_str(pressed_key);
+    } else {
+        debug!("Invalid input: {}", pressed_key);
+    }
+    ControlFlow::Continue(())
+}
+
+/// Selects the label from the input of the user.
+///
+/// # Arguments
+/// * `input` - The input of the user.
+/// * `current_label` - The label of the current task, if any.
+/// * `recent_labels` - The recently used labels, starting with the latest one.
+///
+/// # Returns
+/// The current label if the input is empty, `None` if the input is '-', the recent label if the
+/// input is its number and the input itself otherwise.
+fn select_label(
+    input: &str,
+    current_label: Option<&str>,
+    recent_labels: &[String],
+) -> Option<String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return current_label.map(str::to_string);
+    }
+    if input == "-" {
+        return None;
+    }
+    if let Ok(number) = input.parse::<usize>() {
+        if (1..=recent_labels.len()).contains(&number) {
+            return Some(recent_labels[number - 1].clone());
+        }
+    }
+    Some(input.to_string())
+}
+
+/// Executes the timer.
+///
+/// This function runs the timer for its duration and prints a message when the timer ends.
+///
+/// # Arguments
+/// * `phase` - The phase the timer is running for.
+/// * `timer` - The timer of the phase, which is not started yet.
 /// * `receiver` - The receiver for input events.
-/// * `end_event` - The function to execute when the timer ends.
-fn time_with_progress_bar<F: Fn()>(
-    duration: Duration,
+/// * `label` - The label of the current task, which can be changed by pressing 'l'.
+/// * `recent_labels` - The recently used labels, starting with the latest one.
+/// * `options` - The Pomodoro options containing the timed events.
+/// * `context` - The context of the timed events of the phase.
+/// * `recorder` - The recorder of the end events, if they are recorded instead of started.
+/// * `view` - The view showing the progress and the messages.
+///
+/// # Returns
+/// The history record of the phase or the outcome of the session if the user quit the program.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn execute_timer(
+    phase: Phase,
+    timer: &Timer,
     receiver: &std::sync::mpsc::Receiver<String>,
-    end_event: F,
-) {
-    let timer = Timer::new(duration);
-    let mut bar = ProgressBar::new(duration.as_secs());
-    bar.set_style(
-        ProgressStyle::with_template("[{elapsed}/{eta}] {wide_bar:.cyan/blue} ").unwrap(),
-    );
-    let delta: u64 = 100;
-    let mut cumulative_delta: u64 = 0;
+    label: &mut Option<String>,
+    recent_labels: &[String],
+    options: &PomodoroOptions,
+    context: &EndEventContext,
+    recorder: Option<&EndEventRecorder>,
+    view: &Arc<dyn View>,
+) -> ControlFlow<SessionOutcome, HistoryRecord> {
+    let record = time_with_progress_bar(
+        phase,
+        timer,
+        receiver,
+        label,
+        recent_labels,
+        options,
+        context,
+        recorder,
+        view,
+    )?;
+    view.show_message("Times up!");
+    ControlFlow::Continue(record)
+}
+
+/// Executes the timer and displays a progress bar.
+///
+/// The loop waits for the next input, but at most until the next full second, the next timed
+/// event or the end of the timer, and then updates the progress bar. So it does not poll.
+/// The timer can be paused and resumed using the 'p' and 'r' keys respectively.
+/// Also it can be stopped using the 'q' key and the task label can be changed using the 'l' key.
+/// The keys can be changed with the key bindings of the options, see `KeyBindings`.
+/// While the label is typed, the keys are added to it, but the timer keeps running and the
+/// commands of other interfaces, e.g. `pause`, are still handled.
+/// The timed events of the phase are started in the background when the timer reaches their time.
+/// When the phase is extended, the timed events which are still due are scheduled anew.
+///
+/// # Arguments
+/// * `phase` - The phase the timer is running for.
+/// * `timer` - The timer of the phase, which is not started yet.
+/// * `receiver` - The receiver for input events.
+/// * `label` - The label of the current task, which can be changed by pressing 'l'.
+/// * `recent_labels` - The recently used labels, starting with the latest one.
+/// * `options` - The Pomodoro options containing the timed events.
+/// * `context` - The context of the timed events of the phase.
+/// * `recorder` - The recorder of the end events, if they are recorded instead of started.
+/// * `view` - The view showing the progress and the messages.
+///
+/// # Returns
+/// The history record of the phase, which is marked as quit if the timer was quit before it ended.
+/// If the user quit the program, the outcome of the session is returned instead.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn time_with_progress_bar(
+    phase: Phase,
+    timer: &Timer,
+    receiver: &std::sync::mpsc::Receiver<String>,
+    label: &mut Option<String>,
+    recent_labels: &[String],
+    options: &PomodoroOptions,
+    context: &EndEventContext,
+    recorder: Option<&EndEventRecorder>,
+    view: &Arc<dyn View>,
+) -> ControlFlow<SessionOutcome, HistoryRecord> {
+    let start = Local::now();
+    let mut duration = timer.get_duration();
+    let mut timed_events = options.timed_events_of(phase, duration);
+    view.start_progress(timer);
     timer.start();
-    println!("Press 'p' to pause, 'q' to quit current timer and 's' to skip 1 minute.");
-    let mut control_flow;
-    while timer.get_elapsed_time() < duration {
-        (bar,control_flow) = handle_user_input(receiver, &timer, bar);
-        if control_flow == ControlFlow::Break(()) {
-            return;
+    view.show_message(&help_running(&options.key_bindings));
+    let mut label_editor = LabelEditor::new(label, recent_labels);
+    loop {
+        // The elapsed time of the timer excludes pauses and includes skipped minutes.
+        let elapsed_time = timer.get_elapsed_time();
+        timed_events.retain(|(due, end_event)| {
+            if elapsed_time < *due {
+                return true;
+            }
+            // Events passed by skipping to the end are not started, the end event follows.
+            if elapsed_time < duration {
+                let context = EndEventContext {
+                    label: label_editor.label.clone(),
+                    ..context.clone()
+                };
+                match recorder {
+                    Some(recorder) => recorder.record(EventTrigger::Timed, end_event, &context),
+                    None => start_end_event_in_background(end_event, options, &context, view),
+                }
+            }
+            false
+        });
+        view.update_progress(timer);
+        if elapsed_time >= duration {
+            break;
         }
-        thread::sleep(Duration::from_millis(delta));
-        if !timer.is_paused() {
-            cumulative_delta += delta;
-            if cumulative_delta >= 1000 {
-                cumulative_delta -= 1000;
-                bar.inc(1);
+        let timeout = time_until_next_update(elapsed_time, duration, &timed_events);
+        match timer.clock().recv_timeout(receiver, timeout) {
+            // The commands of other interfaces and ctrl+c are still handled while typing.
+            Ok(input)
+                if label_editor.is_typing()
+                    && input != "ctrl+c"
+                    && KeyBindings::command(&input).is_none() =>
+            {
+                label_editor.type_key(&input, timer, view.as_ref());
             }
+            Ok(input) => {
+                let key_bindings = &options.key_bindings;
+                let flow =
+                    handle_user_input(input, timer, &mut label_editor, key_bindings, view.as_ref());
+                if let ControlFlow::Break(action) = flow {
+                    label_editor.cancel(view.as_ref());
+                    view.finish_progress();
+                    if action == Action::QuitApp {
+                        return ControlFlow::Break(SessionOutcome::Quit);
+                    }
+                    let label = label_editor.label.clone();
+                    let record = create_history_record(phase, duration, start, timer, true, label);
+                    return ControlFlow::Continue(record);
+                }
+                if timer.get_duration() != duration {
+                    duration = timer.get_duration();
+                    let elapsed_time = timer.get_elapsed_time();
+                    timed_events = options.timed_events_of(phase, duration);
+                    timed_events.retain(|(due, _)| *due > elapsed_time);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            // Without any input, the timer just runs until its end.
+            Err(RecvTimeoutError::Disconnected) => timer.clock().sleep(timeout),
+        }
+    }
+    label_editor.cancel(view.as_ref());
+    view.finish_progress();
+    let label = label_editor.label.clone();
+    ControlFlow::Continue(create_history_record(
+        phase, duration, start, timer, false, label,
+    ))
+}
+
+/// The task label of a running phase and the label typed by the user to change it.
+struct LabelEditor<'a> {
+    /// The label of the current task.
+    label: &'a mut Option<String>,
+    /// The label typed by the user after pressing 'l', until enter is pressed.
+    typed_label: Option<String>,
+    /// The recently used labels, starting with the latest one.
+    recent_labels: &'a [String],
+}
+
+impl<'a> LabelEditor<'a> {
+    /// Creates the editor of the label. The label is not typed until `start_typing` is called.
+    fn new(label: &'a mut Option<String>, recent_labels: &'a [String]) -> Self {
+        LabelEditor {
+            label,
+            typed_label: None,
+            recent_labels,
+        }
+    }
+
+    /// Indicates whether the label is typed, so the keys are added to it.
+    fn is_typing(&self) -> bool {
+        self.typed_label.is_some()
+    }
+
+    /// Starts typing the label and shows the question and the recent labels.
+    fn start_typing(&mut self, view: &dyn View) {
+        show_label_question(self.label.as_deref(), self.recent_labels, view);
+        self.typed_label = Some(String::new());
+        view.show_input(Some(""));
+    }
+
+    /// Adds the pressed key to the label typed while the timer is running.
+    ///
+    /// When enter is pressed, the label is selected like in `ask_for_label` and typing ends.
+    ///
+    /// # Arguments
+    /// * `pressed_key` - The pressed key.
+    /// * `timer` - The timer of the phase.
+    /// * `view` - The view showing the typed label.
+    fn type_key(&mut self, pressed_key: &str, timer: &Timer, view: &dyn View) {
+        let Some(input) = &mut self.typed_label else {
+            return;
+        };
+        if type_label(input, pressed_key).is_continue() {
+            view.show_input(Some(input));
+            return;
+        }
+        view.show_input(None);
+        *self.label = select_label(input, self.label.as_deref(), self.recent_labels);
+        self.typed_label = None;
+        match self.label {
+            Some(label) => view.show_message(&format!("Task: {}", label)),
+            None => view.show_message("Task label removed."),
+        }
+        view.reset_progress(timer);
+    }
+
+    /// Ends typing the label when the phase ends before enter is pressed. The label is not
+    /// changed.
+    fn cancel(&mut self, view: &dyn View) {
+        if self.typed_label.take().is_some() {
+            view.show_input(None);
+            view.show_message("The task label was not changed.");
         }
     }
-    bar.finish();
-    end_event();
+}
+
+/// Gets the time until the progress bar has to be updated or a timed event has to be started.
+///
+/// # Arguments
+/// * `elapsed_time` - The elapsed time of the timer.
+/// * `duration` - The duration of the timer.
+/// * `timed_events` - The timed events which have not been started yet.
+///
+/// # Returns
+/// The time until the next full second, the next timed event or the end of the timer.
+fn time_until_next_update(
+    elapsed_time: Duration,
+    duration: Duration,
+    timed_events: &[(Duration, &EndEvent)],
+) -> Duration {
+    let next_second = Duration::from_secs(elapsed_time.as_secs() + 1);
+    let next_update = timed_events
+        .iter()
+        .map(|(due, _)| *due)
+        .filter(|due| *due > elapsed_time)
+        .fold(next_second.min(duration), Duration::min);
+    next_update.saturating_sub(elapsed_time)
+}
+
+/// Creates the history record of a phase which ends now.
+///
+/// # Arguments
+/// * `phase` - The phase the timer was running for.
+/// * `duration` - The planned duration of the phase.
+/// * `start` - The wall-clock time when the phase started.
+/// * `timer` - The timer of the phase.
+/// * `quit` - Whether the phase was quit before it ended.
+/// * `label` - The label of the task worked on during the phase, if any.
+fn create_history_record(
+    phase: Phase,
+    duration: Duration,
+    start: DateTime<Local>,
+    timer: &Timer,
+    quit: bool,
+    label: Option<String>,
+) -> HistoryRecord {
+    let end = Local::now();
+    let paused_time = timer.get_paused_time();
+    let wall_clock_time = (end - start).to_std().unwrap_or_default();
+    HistoryRecord {
+        start,
+        end,
+        phase,
+        planned_seconds: duration.as_secs(),
+        actual_seconds: wall_clock_time.saturating_sub(paused_time).as_secs(),
+        paused_seconds: paused_time.as_secs(),
+        skipped_seconds: timer.get_skipped_time().as_secs(),
+        quit,
+        label,
+    }
+}
+
+/// Generates the help text which is shown while the timer is running.
+fn help_running(key_bindings: &KeyBindings) -> String {
+    let skip = describe_minutes("skip", key_bindings.skip_minutes);
+    let extend = describe_minutes("extend the timer by", key_bindings.extend_minutes);
+    format!(
+        "Press {}.",
+        key_bindings.help(&[
+            (Action::TogglePause, "pause"),
+            (Action::Pause, "pause"),
+            (Action::QuitPhase, "quit current timer"),
+            (Action::Skip(key_bindings.skip_minutes), &skip),
+            (Action::Extend(key_bindings.extend_minutes), &extend),
+            (Action::NextPhase, "end the phase now"),
+            (Action::ChangeLabel, "change the task label"),
+        ])
+    )
+}
+
+/// Generates the help text which is shown when an interrupted session can be resumed.
+fn help_resume(key_bindings: &KeyBindings) -> String {
+    format!(
+        "Press {}.",
+        key_bindings.help(&[
+            (Action::Confirm, "resume"),
+            (Action::QuitPhase, "start over"),
+        ])
+    )
+}
+
+/// Generates the help text which is shown while the timer is paused.
+pub(crate) fn help_paused(key_bindings: &KeyBindings) -> String {
+    format!(
+        "Press {}.",
+        key_bindings.help(&[
+            (Action::TogglePause, "resume"),
+            (Action::Resume, "resume"),
+            (Action::QuitPhase, "quit current timer"),
+        ])
+    )
 }
 
 /// Handles user input during the timer execution.
 ///
-/// This function processes user input to pause, resume, quit, or skip time in the timer.
-/// It updates the progress bar accordingly.
+/// This function processes user input to pause, resume, quit, skip time, extend or end the phase
+/// or to start typing the task label. The keys of the actions are looked up in the key bindings.
+/// It updates the progress shown by the view accordingly.
 ///
 /// # Arguments
-/// * `receiver` - The receiver for input events.
+/// * `input` - The received input.
 /// * `timer` - The timer instance.
-/// * `bar` - The progress bar instance.
+/// * `label_editor` - The editor of the task label, which starts typing when the label is
+///   changed.
+/// * `key_bindings` - The keys of the actions.
+/// * `view` - The view showing the progress and the messages.
 ///
 /// # Returns
-/// A tuple containing the updated progress bar and a control flow indicating whether to continue or break.
-fn handle_user_input(receiver: &std::sync::mpsc::Receiver<String>, timer: &Timer, mut bar: ProgressBar) -> (ProgressBar,  ControlFlow<()>)
- {
-    if let Ok(input) = receiver.try_recv() {
-        if input == "p" {
+/// A control flow which breaks with the action if the timer or the program is quit.
+fn handle_user_input(
+    input: String,
+    timer: &Timer,
+    label_editor: &mut LabelEditor,
+    key_bindings: &KeyBindings,
+    view: &dyn View,
+) -> ControlFlow<Action> {
+    let action = match key_bindings.action(&input) {
+        Some(Action::TogglePause) if timer.is_paused() => Some(Action::Resume),
+        Some(Action::TogglePause) => Some(Action::Pause),
+        action => action,
+    };
+    match action {
+        Some(Action::Pause) => {
             timer.pause();
-            println!("Timer paused.");
-            println!("Press 'r' to resume, 'q' to quit current timer.");
-        } else if input == "r" {
+            view.show_message("Timer paused.");
+            view.show_message(&help_paused(key_bindings));
+        }
+        Some(Action::Resume) => {
             timer.resume();
-            println!("Timer resumed.");
-            println!(
-                "Press 'p' to pause, 'q' to quit current timer and 's' to skip 1 minute."
-            );
-            bar = bar.with_elapsed(timer.get_elapsed_time());
-            bar.reset_eta();
-        } else if input == "q" {
+            view.show_message("Timer resumed.");
+            view.show_message(&help_running(key_bindings));
+            view.reset_progress(timer);
+        }
+        Some(Action::QuitPhase) => {
             // Return early to not execute the end event.
-            println!("Exiting the current timer.");
-            return (bar, ControlFlow::Break(()));
-        } else if input == "s" {
-            println!("Skipping 1 minute.");
-            log::trace!("Skipping 1 minute.");
-            timer.skip(Duration::from_secs(60));
-            log::trace!("Skipping 1 minute. Updating progress bar.");
-            bar = bar.with_elapsed(timer.get_elapsed_time());
-            bar.set_position(timer.get_elapsed_time().as_secs());
-            bar.reset_eta();
+            view.show_message("Exiting the current timer.");
+            return ControlFlow::Break(Action::QuitPhase);
+        }
+        Some(Action::Skip(minutes)) => {
+            view.show_message(&format!("{}.", describe_minutes("Skipping", minutes)));
+            log::trace!("Skipping {} minutes.", minutes);
+            timer.skip(Duration::from_secs(minutes.saturating_mul(60)));
+            log::trace!("Skipping {} minutes. Updating progress bar.", minutes);
+            view.reset_progress(timer);
             log::trace!("Progress bar updated.");
-        } else if input == "ctrl+c" {
-            println!("Exiting the program.");
-            std::process::exit(0);
-        } else {
-            debug!("Invalid input: {}", input);
         }
-        log::debug!("Elapsed time: {:?}", timer.get_elapsed_time());
+        Some(Action::Extend(minutes)) => {
+            view.show_message(&format!(
+                "{}.",
+                describe_minutes("Extending the timer by", minutes)
+            ));
+            timer.extend(Duration::from_secs(minutes.saturating_mul(60)));
+            view.reset_progress(timer);
+        }
+        Some(Action::NextPhase) => {
+            // The rest of the phase is skipped, so the end event is executed as usual.
+            view.show_message("Ending the phase now.");
+            timer.skip(timer.get_remaining_time());
+            view.reset_progress(timer);
+        }
+        Some(Action::ChangeLabel) => {
+            // The following keys are added to the label, see `LabelEditor::type_key`.
+            label_editor.start_typing(view);
+        }
+        Some(Action::QuitApp) => return ControlFlow::Break(Action::QuitApp),
+        Some(Action::TogglePause | Action::Confirm) | None => debug!("Invalid input: {}", input),
     }
-    (bar, ControlFlow::Continue(()))
+    log::debug!("Elapsed time: {:?}", timer.get_elapsed_time());
+    ControlFlow::Continue(())
+}
+
+#[test]
+fn test_select_label() {
+    let recent_labels = vec!["coding".to_string(), "review".to_string()];
+    assert_eq!(
+        select_label("", Some("writing"), &recent_labels),
+        Some("writing".to_string())
+    );
+    assert_eq!(select_label("  ", None, &recent_labels), None);
+    assert_eq!(select_label("-", Some("writing"), &recent_labels), None);
+    assert_eq!(
+        select_label("2", None, &recent_labels),
+        Some("review".to_string())
+    );
+    assert_eq!(
+        select_label("3", None, &recent_labels),
+        Some("3".to_string())
+    );
+    assert_eq!(
+        select_label(" docs ", None, &recent_labels),
+        Some("docs".to_string())
+    );
+}
+
+#[test]
+fn test_time_until_next_update() {
+    let duration = Duration::from_secs(60);
+    let event = EndEvent::None;
+    let timed_events = vec![(Duration::from_millis(30_500), &event)];
+    assert_eq!(
+        time_until_next_update(Duration::from_millis(10_250), duration, &timed_events),
+        Duration::from_millis(750)
+    );
+    assert_eq!(
+        time_until_next_update(Duration::from_millis(30_200), duration, &timed_events),
+        Duration::from_millis(300)
+    );
+    assert_eq!(
+        time_until_next_update(Duration::from_millis(59_900), duration, &[]),
+        Duration::from_millis(100)
+    );
+}
+
+#[test]
+fn test_help() {
+    let key_bindings = KeyBindings::default();
+    assert_eq!(
+        help_running(&key_bindings),
+        "Press space or 'p' to pause, 'q' to quit current timer, 's' to skip 1 minute, 'e' to extend the timer by 5 minutes, 'n' to end the phase now and 'l' to change the task label."
+    );
+    assert_eq!(
+        help_paused(&key_bindings),
+        "Press space or 'r' to resume and 'q' to quit current timer."
+    );
+    assert_eq!(
+        help_resume(&key_bindings),
+        "Press enter to resume and 'q' to start over."
+    );
+}
+
+#[test]
+fn test_label_is_typed_while_timer_runs() {
+    use crate::clock::VirtualClock;
+    use crate::end_events::{EventTime, TimedEvent};
+    use crate::pomo_info::PomoInfo;
+    use crate::view::TerminalView;
+
+    let options = PomodoroOptions {
+        timed_events: vec![TimedEvent {
+            phase: Phase::Pomodoro,
+            at: EventTime::SecondsBeforeEnd(60),
+            event: EndEvent::LockScreen,
+        }],
+        ..PomodoroOptions::default()
+    };
+    let context = EndEventContext {
+        phase: Phase::Pomodoro,
+        next_phase: Phase::Pomodoro,
+        pomo_info: PomoInfo::from_options(&options, 0),
+        counter: 0,
+        label: None,
+    };
+    let view: Arc<dyn View> = Arc::new(TerminalView::default());
+    let run = |inputs: &[&str]| {
+        let clock = Arc::new(VirtualClock::new());
+        let recorder = EndEventRecorder::new(clock.clone());
+        let timer = Timer::with_clock(Duration::from_secs(25 * 60), clock);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        for input in inputs {
+            sender.send(input.to_string()).unwrap();
+        }
+        let mut label = Some("coding".to_string());
+        let record = time_with_progress_bar(
+            Phase::Pomodoro,
+            &timer,
+            &receiver,
+            &mut label,
+            &["review".to_string()],
+            &options,
+            &context,
+            Some(&recorder),
+            &view,
+        );
+        (record, label, recorder.events().len())
+    };
+
+    // The keys are added to the label, but the commands are handled.
+    let (record, label, timed_events) = run(&["l", "d", "o", "c", "s", "skip 2", "p", "\n", "s"]);
+    let record = record.continue_value().unwrap();
+    assert_eq!(label, Some("docsp".to_string()));
+    assert_eq!(record.skipped_seconds, 3 * 60);
+    assert!(!record.quit);
+    assert_eq!(timed_events, 1);
+
+    // The phase ends while the label is typed, so the label is not changed.
+    let (record, label, timed_events) = run(&["l", "2", "pause", "resume"]);
+    let record = record.continue_value().unwrap();
+    assert_eq!(label, Some("coding".to_string()));
+    assert_eq!(record.label, Some("coding".to_string()));
+    assert!(!record.quit);
+    assert_eq!(timed_events, 1);
+
+    let (record, label, _) = run(&["l", "1", "\n"]);
+    assert_eq!(label, Some("review".to_string()));
+    assert_eq!(
+        record.continue_value().unwrap().label,
+        Some("review".to_string())
+    );
+    assert!(run(&["l", "quit"]).0.continue_value().unwrap().quit);
+
+    // ctrl+c is not added to the label, but quits the program.
+    let (record, label, timed_events) = run(&["l", "d", "ctrl+c"]);
+    assert_eq!(record.break_value(), Some(SessionOutcome::Quit));
+    assert_eq!(label, Some("coding".to_string()));
+    assert_eq!(timed_events, 0);
 }
@@ -0,0 +1,193 @@
+//! This module contains the clocks which measure the time of the timer.
+//!
+//! The `SystemClock` is used by default. The `ScaledClock` runs faster than the system clock and
+//! the `VirtualClock` does not wait at all, so a whole cycle of Pomodoros and breaks can be
+//! simulated in seconds, see `simulation`.
+use std::sync::mpsc::{Receiver, RecvTimeoutError, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A source of time for the timer.
+pub trait Clock: Send + Sync {
+    /// Gets the current instant of the clock.
+    fn now(&self) -> Instant;
+
+    /// Waits until the duration has passed on the clock.
+    fn sleep(&self, duration: Duration);
+
+    /// Waits for the next input of the channel, but at most until the timeout has passed on the
+    /// clock.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RecvTimeoutError::Timeout` if no input was received in time and
+    /// `RecvTimeoutError::Disconnected` if no input can be received anymore.
+    fn recv_timeout(
+        &self,
+        receiver: &Receiver<String>,
+        timeout: Duration,
+    ) -> Result<String, RecvTimeoutError>;
+}
+
+/// The monotonic clock of the system.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+
+    fn recv_timeout(
+        &self,
+        receiver: &Receiver<String>,
+        timeout: Duration,
+    ) -> Result<String, RecvTimeoutError> {
+        receiver.recv_timeout(timeout)
+    }
+}
+
+/// A clock which runs faster than the system clock by a constant factor.
+#[derive(Debug, Clone, Copy)]
+pub struct ScaledClock {
+    start: Instant,
+    speed: f64,
+}
+
+impl ScaledClock {
+    /// Creates a clock which runs `speed` times faster than the system clock, e.g. 60 times to
+    /// let a minute pass every second.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the speed is not positive.
+    pub fn new(speed: f64) -> Self {
+        assert!(speed > 0.0, "The speed of a clock must be positive.");
+        ScaledClock {
+            start: Instant::now(),
+            speed,
+        }
+    }
+}
+
+impl Clock for ScaledClock {
+    fn now(&self) -> Instant {
+        self.start + self.start.elapsed().mul_f64(self.speed)
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration.div_f64(self.speed));
+    }
+
+    fn recv_timeout(
+        &self,
+        receiver: &Receiver<String>,
+        timeout: Duration,
+    ) -> Result<String, RecvTimeoutError> {
+        receiver.recv_timeout(timeout.div_f64(self.speed))
+    }
+}
+
+/// A clock which only advances when it sleeps or is advanced explicitly.
+#[derive(Debug)]
+pub struct VirtualClock {
+    start: Instant,
+    elapsed_time: Mutex<Duration>,
+}
+
+impl VirtualClock {
+    /// Creates a virtual clock starting now.
+    pub fn new() -> Self {
+        VirtualClock {
+            start: Instant::now(),
+            elapsed_time: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Advances the clock by the duration.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed_time.lock().unwrap() += duration;
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        self.start + *self.elapsed_time.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+
+    /// Takes the input which has already been sent. Otherwise the timeout passes at once.
+    ///
+    /// If no input can be received anymore, the clock does not advance, like a real channel
+    /// which returns at once. The caller sleeps instead, see `time_with_progress_bar`.
+    fn recv_timeout(
+        &self,
+        receiver: &Receiver<String>,
+        timeout: Duration,
+    ) -> Result<String, RecvTimeoutError> {
+        match receiver.try_recv() {
+            Ok(input) => Ok(input),
+            Err(TryRecvError::Empty) => {
+                self.advance(timeout);
+                Err(RecvTimeoutError::Timeout)
+            }
+            Err(TryRecvError::Disconnected) => Err(RecvTimeoutError::Disconnected),
+        }
+    }
+}
+
+#[test]
+fn test_clocks() {
+    let clock = VirtualClock::new();
+    let start = clock.now();
+    clock.sleep(Duration::from_secs(25 * 60));
+    clock.advance(Duration::from_millis(500));
+    assert_eq!(
+        clock.now() - start,
+        Duration::from_millis(25 * 60 * 1000 + 500)
+    );
+    let (sender, receiver) = std::sync::mpsc::channel();
+    sender.send("p".to_string()).unwrap();
+    assert_eq!(
+        clock.recv_timeout(&receiver, Duration::from_secs(60)),
+        Ok("p".to_string())
+    );
+    assert_eq!(
+        clock.recv_timeout(&receiver, Duration::from_secs(60)),
+        Err(RecvTimeoutError::Timeout)
+    );
+    assert_eq!(
+        clock.now() - start,
+        Duration::from_millis(26 * 60 * 1000 + 500)
+    );
+    drop(sender);
+    assert_eq!(
+        clock.recv_timeout(&receiver, Duration::from_secs(60)),
+        Err(RecvTimeoutError::Disconnected)
+    );
+    assert_eq!(
+        clock.now() - start,
+        Duration::from_millis(26 * 60 * 1000 + 500)
+    );
+
+    let clock = ScaledClock::new(1000.0);
+    let start = clock.now();
+    clock.sleep(Duration::from_secs(1));
+    let elapsed_time = clock.now() - start;
+    assert!(elapsed_time >= Duration::from_secs(1));
+    assert!(elapsed_time < Duration::from_secs(60));
+}
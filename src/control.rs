@@ -0,0 +1,360 @@
+//! This module contains the control interface, which allows to control the running timer from
+//! other processes, e.g. a keybinding of the window manager.
+//!
+//! The running timer listens on a Unix domain socket. Every line sent to the socket is a JSON
+//! command like `{"command":"pause"}` and is answered with a single JSON line like `{"ok":true}`.
+//! The commands `pause`, `resume`, `skip` and `quit` perform the same actions as the keys `p`,
+//! `r`, `s` and `q` by default, regardless of the key bindings. `skip` skips 1 minute unless
+//! `minutes` is given, e.g. `{"command":"skip","minutes":5}`. The command `status` returns the
+//! `SessionStatus` of the running timer.
+use crate::key_bindings::MAX_MINUTES;
+use crate::session::{SessionStatus, StatusHandle};
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+/// Represents a command sent to the control socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Subcommand)]
+#[serde(tag = "command", rename_all = "camelCase")]
+pub enum ControlCommand {
+    /// Pause the running timer.
+    Pause,
+    /// Resume the paused timer.
+    Resume,
+    /// Skip minutes of the running timer.
+    Skip {
+        /// The number of minutes to skip, at most one day.
+        #[arg(default_value_t = 1)]
+        #[serde(default = "default_skip_minutes")]
+        minutes: u64,
+    },
+    /// Quit the running timer.
+    Quit,
+    /// Get the status of the timer.
+    Status,
+}
+
+impl ControlCommand {
+    /// Gets the input which is sent to the session, see `key_bindings`.
+    fn input(&self) -> Option<String> {
+        match self {
+            ControlCommand::Pause => Some("pause".to_string()),
+            ControlCommand::Resume => Some("resume".to_string()),
+            ControlCommand::Skip { minutes } => Some(format!("skip {}", minutes)),
+            ControlCommand::Quit => Some("quit".to_string()),
+            ControlCommand::Status => None,
+        }
+    }
+}
+
+/// Gets the number of minutes skipped by the `skip` command if none is specified.
+fn default_skip_minutes() -> u64 {
+    1
+}
+
+/// Represents the answer to a `ControlCommand`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ControlResponse {
+    /// Indicates whether the command was accepted.
+    pub ok: bool,
+    /// The reason why the command was not accepted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The status of the timer, only sent for the `status` command.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<SessionStatus>,
+}
+
+/// Gets the path to the control socket of the current user.
+///
+/// The socket is located in `$XDG_RUNTIME_DIR`. If it is not set, a folder of the user in the
+/// temporary folder is used, which is only accessible by the user, see `start_control_server`.
+pub fn get_filepath_socket() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(folder) if !folder.is_empty() => {
+            PathBuf::from(folder).join("locking-pomodoro-timer.sock")
+        }
+        _ => {
+            let user = std::env::var("USER").unwrap_or_default();
+            std::env::temp_dir()
+                .join(format!("locking-pomodoro-timer-{}", user))
+                .join("control.sock")
+        }
+    }
+}
+
+/// Starts listening for commands on the control socket.
+///
+/// The commands are handled in a separate thread for every connection. Actions are sent as input
+/// through the `sender`, so they are handled like the keys pressed in the terminal.
+///
+/// The folder of the socket is created if it does not exist. It must only be accessible by the
+/// user, so no other user can connect to the socket, not even before its permissions are set.
+///
+/// # Arguments
+///
+/// * `filepath_socket` - The path to the control socket.
+/// * `sender` - The sender of the input channel of the session.
+/// * `status` - The handle to query the status of the session.
+///
+/// # Errors
+///
+/// Returns an error if another timer is already listening on the socket, if the folder of the
+/// socket is accessible by other users or if the socket could not be created.
+pub fn start_control_server(
+    filepath_socket: &Path,
+    sender: Sender<String>,
+    status: StatusHandle,
+) -> Result<()> {
+    if let Some(folder) = filepath_socket.parent() {
+        create_private_folder(folder)?;
+    }
+    if filepath_socket.exists() {
+        if UnixStream::connect(filepath_socket).is_ok() {
+            bail!(
+                "Another timer is already listening on the control socket: {:?}",
+                filepath_socket
+            );
+        }
+        // The socket was left behind by a timer which did not exit cleanly.
+        std::fs::remove_file(filepath_socket)
+            .with_context(|| format!("Failed to remove stale socket: {:?}", filepath_socket))?;
+    }
+    let listener = UnixListener::bind(filepath_socket)
+        .with_context(|| format!("Failed to bind control socket: {:?}", filepath_socket))?;
+    std::fs::set_permissions(filepath_socket, std::fs::Permissions::from_mode(0o600))
+        .with_context(|| format!("Failed to restrict control socket: {:?}", filepath_socket))?;
+    std::thread::Builder::new()
+        .name("control_server".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let sender = sender.clone();
+                        let status = status.clone();
+                        std::thread::spawn(move || handle_connection(stream, &sender, &status));
+                    }
+                    Err(e) => warn!("Failed to accept control connection: {}", e),
+                }
+            }
+        })
+        .context("Failed to spawn control server thread.")?;
+    Ok(())
+}
+
+/// Creates the folder with access only for the user, or checks that an existing folder has no
+/// access for other users.
+///
+/// # Errors
+///
+/// Returns an error if the folder could not be created or if it is accessible by other users.
+fn create_private_folder(folder: &Path) -> Result<()> {
+    match std::fs::symlink_metadata(folder) {
+        Ok(metadata) => {
+            if !metadata.is_dir() || metadata.permissions().mode() & 0o077 != 0 {
+                bail!(
+                    "The folder of the control socket is accessible by other users: {:?}",
+                    folder
+                );
+            }
+            Ok(())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => std::fs::DirBuilder::new()
+            .mode(0o700)
+            .create(folder)
+            .with_context(|| {
+                format!(
+                    "Failed to create the folder of the control socket: {:?}",
+                    folder
+                )
+            }),
+        Err(e) => Err(e).with_context(|| {
+            format!(
+                "Failed to read the folder of the control socket: {:?}",
+                folder
+            )
+        }),
+    }
+}
+
+/// Handles the commands of a single connection until it is closed.
+fn handle_connection(stream: UnixStream, sender: &Sender<String>, status: &StatusHandle) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(e) => {
+            error!("Failed to clone control connection: {}", e);
+            return;
+        }
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = handle_command(&line, sender, status);
+        let mut answer = serde_json::to_string(&response).expect("Failed to serialize response.");
+        answer.push('\n');
+        if writer.write_all(answer.as_bytes()).is_err() {
+            break;
+        }
+    }
+}
+
+/// Handles a single line sent to the control socket.
+///
+/// # Arguments
+///
+/// * `line` - The JSON command.
+/// * `sender` - The sender of the input channel of the session.
+/// * `status` - The handle to query the status of the session.
+///
+/// # Returns
+///
+/// The response to send back.
+pub fn handle_command(
+    line: &str,
+    sender: &Sender<String>,
+    status: &StatusHandle,
+) -> ControlResponse {
+    debug!("Received control command: {}", line);
+    let command: ControlCommand = match serde_json::from_str(line) {
+        Ok(command) => command,
+        Err(e) => {
+            return ControlResponse {
+                ok: false,
+                error: Some(format!("Invalid command: {}", e)),
+                status: None,
+            }
+        }
+    };
+    if let ControlCommand::Skip { minutes } = command {
+        if minutes > MAX_MINUTES {
+            return ControlResponse {
+                ok: false,
+                error: Some(format!("At most {} minutes can be skipped.", MAX_MINUTES)),
+                status: None,
+            };
+        }
+    }
+    match command.input() {
+        Some(input) => match sender.send(input) {
+            Ok(()) => ControlResponse {
+                ok: true,
+                error: None,
+                status: None,
+            },
+            Err(_) => ControlResponse {
+                ok: false,
+                error: Some("The session has ended.".to_string()),
+                status: None,
+            },
+        },
+        None => ControlResponse {
+            ok: true,
+            error: None,
+            status: Some(status.status()),
+        },
+    }
+}
+
+/// Sends a command to the timer listening on the control socket.
+///
+/// # Arguments
+///
+/// * `filepath_socket` - The path to the control socket.
+/// * `command` - The command to send.
+///
+/// # Errors
+///
+/// Returns an error if no timer is listening on the socket or if the answer is invalid.
+pub fn send_command(filepath_socket: &Path, command: ControlCommand) -> Result<ControlResponse> {
+    let mut stream = UnixStream::connect(filepath_socket).with_context(|| {
+        format!(
+            "Failed to connect to the control socket {:?}. Is the timer running?",
+            filepath_socket
+        )
+    })?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut line = serde_json::to_string(&command)?;
+    line.push('\n');
+    stream
+        .write_all(line.as_bytes())
+        .context("Failed to send the command.")?;
+    let mut answer = String::new();
+    BufReader::new(stream)
+        .read_line(&mut answer)
+        .context("Failed to receive the answer.")?;
+    serde_json::from_str(&answer).with_context(|| format!("Invalid answer: {:?}", answer))
+}
+
+#[test]
+fn test_control_socket() {
+    let folder = std::env::temp_dir().join(format!("pomodoro_control_test_{}", std::process::id()));
+    let filepath_socket = folder.join("control.sock");
+    let (sender, receiver) = std::sync::mpsc::channel();
+    start_control_server(&filepath_socket, sender, StatusHandle::default()).unwrap();
+    assert!(start_control_server(
+        &filepath_socket,
+        std::sync::mpsc::channel().0,
+        StatusHandle::default()
+    )
+    .is_err());
+
+    for (command, input) in [
+        (ControlCommand::Pause, "pause"),
+        (ControlCommand::Resume, "resume"),
+        (ControlCommand::Skip { minutes: 5 }, "skip 5"),
+        (ControlCommand::Quit, "quit"),
+    ] {
+        let response = send_command(&filepath_socket, command).unwrap();
+        assert!(response.ok);
+        assert_eq!(receiver.recv().unwrap(), input);
+    }
+    let response = send_command(&filepath_socket, ControlCommand::Status).unwrap();
+    let status = response.status.unwrap();
+    assert_eq!(status.phase, None);
+    assert_eq!(status.remaining_seconds, 0);
+
+    let response = send_command(&filepath_socket, ControlCommand::Skip { minutes: 1441 }).unwrap();
+    assert!(!response.ok);
+
+    let send_line = |line: &[u8]| {
+        let mut stream = UnixStream::connect(&filepath_socket).unwrap();
+        stream.write_all(line).unwrap();
+        let mut answer = String::new();
+        BufReader::new(stream).read_line(&mut answer).unwrap();
+        serde_json::from_str::<ControlResponse>(&answer).unwrap()
+    };
+    // Without minutes, 1 minute is skipped.
+    assert!(send_line(b"{\"command\":\"skip\"}\n").ok);
+    assert_eq!(receiver.recv().unwrap(), "skip 1");
+    let response = send_line(b"{\"command\":\"dance\"}\n");
+    assert!(!response.ok);
+    assert!(response.error.unwrap().starts_with("Invalid command"));
+    assert_eq!(
+        std::fs::metadata(&folder).unwrap().permissions().mode() & 0o777,
+        0o700
+    );
+    std::fs::remove_dir_all(&folder).unwrap();
+
+    // A folder which other users can access is not used.
+    std::fs::create_dir(&folder).unwrap();
+    std::fs::set_permissions(&folder, std::fs::Permissions::from_mode(0o755)).unwrap();
+    assert!(start_control_server(
+        &filepath_socket,
+        std::sync::mpsc::channel().0,
+        StatusHandle::default()
+    )
+    .is_err());
+    std::fs::remove_dir_all(&folder).unwrap();
+}
@@ -0,0 +1,296 @@
+//! This module publishes the running timer as D-Bus service, so desktop widgets like GNOME
+//! extensions, KDE plasmoids or waybar modules can show and control it.
+//!
+//! The object `/org/pomodoro/Timer` is published on the session bus under the name
+//! `org.pomodoro.Timer`. Its interface `org.pomodoro.Timer` has the methods `Pause`, `Resume`,
+//! `Skip` and `Stop`, which perform the same actions as the keys `p`, `r`, `s` and `q` by
+//! default, the properties `Phase`, `Remaining` and `Counter` and the signal `PhaseChanged`.
+//!
+//! # Examples
+//!
+//! ```sh
+//! busctl --user call org.pomodoro.Timer /org/pomodoro/Timer org.pomodoro.Timer Pause
+//! busctl --user get-property org.pomodoro.Timer /org/pomodoro/Timer org.pomodoro.Timer Remaining
+//! ```
+use crate::session::{Phase, PhaseEvent, StatusHandle};
+use anyhow::{Context, Result};
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+use zbus::blocking::connection::Builder;
+use zbus::blocking::Connection;
+use zbus::{fdo, interface, SignalContext};
+
+/// The well-known name of the service on the bus.
+pub const SERVICE_NAME: &str = "org.pomodoro.Timer";
+/// The path of the timer object.
+pub const OBJECT_PATH: &str = "/org/pomodoro/Timer";
+
+/// The `org.pomodoro.Timer` interface of the timer object.
+struct TimerInterface {
+    sender: Mutex<Sender<String>>,
+    status: StatusHandle,
+}
+
+impl TimerInterface {
+    /// Sends the input to the session.
+    fn send_input(&self, input: &str) -> fdo::Result<()> {
+        self.sender
+            .lock()
+            .unwrap()
+            .send(input.to_string())
+            .map_err(|_| fdo::Error::Failed("The session has ended.".to_string()))
+    }
+}
+
+#[interface(name = "org.pomodoro.Timer")]
+impl TimerInterface {
+    /// Pauses the running timer.
+    fn pause(&self) -> fdo::Result<()> {
+        self.send_input("pause")
+    }
+
+    /// Resumes the paused timer.
+    fn resume(&self) -> fdo::Result<()> {
+        self.send_input("resume")
+    }
+
+    /// Skips 1 minute of the running timer.
+    fn skip(&self) -> fdo::Result<()> {
+        self.send_input("skip 1")
+    }
+
+    /// Quits the running timer.
+    fn stop(&self) -> fdo::Result<()> {
+        self.send_input("quit")
+    }
+
+    /// The running phase, e.g. "pomodoro" or "shortBreak", or an empty string if no timer is running.
+    #[zbus(property)]
+    fn phase(&self) -> String {
+        phase_name(self.status.status().phase)
+    }
+
+    /// The remaining time of the running phase in seconds.
+    ///
+    /// The property changes every second, so no change signal is emitted. Widgets should poll it.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn remaining(&self) -> u64 {
+        self.status.status().remaining_seconds
+    }
+
+    /// The number of Pomodoros completed in this session.
+    #[zbus(property)]
+    fn counter(&self) -> i32 {
+        self.status.status().counter
+    }
+
+    /// Emitted when a phase starts and with an empty phase when the session ends.
+    #[zbus(signal, name = "PhaseChanged")]
+    async fn emit_phase_changed(
+        ctxt: &SignalContext<'_>,
+        phase: &str,
+        counter: i32,
+    ) -> zbus::Result<()>;
+}
+
+/// Gets the name of the phase used on the bus.
+fn phase_name(phase: Option<Phase>) -> String {
+    phase
+        .and_then(|phase| serde_json::to_value(phase).ok())
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Represents the published timer object.
+pub struct DbusService {
+    connection: Connection,
+}
+
+impl DbusService {
+    /// Publishes the timer object on the session bus.
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - The sender of the input channel of the session.
+    /// * `status` - The handle to query the status of the session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the session bus is not available or if the name is already taken,
+    /// e.g. by another running timer.
+    pub fn start_on_session_bus(sender: Sender<String>, status: StatusHandle) -> Result<Self> {
+        let builder = Builder::session().context("Failed to connect to the session bus.")?;
+        Self::start(builder, sender, status)
+    }
+
+    /// Publishes the timer object on the bus of the connection builder.
+    ///
+    /// This allows to publish the timer on another bus than the session bus, e.g. a private
+    /// `dbus-daemon` for testing.
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - The builder of the connection to the bus.
+    /// * `sender` - The sender of the input channel of the session.
+    /// * `status` - The handle to query the status of the session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection could not be established or if the name is already taken.
+    pub fn start(
+        builder: Builder<'_>,
+        sender: Sender<String>,
+        status: StatusHandle,
+    ) -> Result<Self> {
+        let interface = TimerInterface {
+            sender: Mutex::new(sender),
+            status,
+        };
+        let connection = builder
+            .name(SERVICE_NAME)
+            .context("Invalid service name.")?
+            .serve_at(OBJECT_PATH, interface)
+            .context("Invalid object path.")?
+            .build()
+            .with_context(|| format!("Failed to publish {} on the bus.", SERVICE_NAME))?;
+        Ok(DbusService { connection })
+    }
+
+    /// Gets the connection to the bus.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Emits the signals for the phase event.
+    ///
+    /// The `Phase` and `Counter` properties are announced as changed for every event. The
+    /// `PhaseChanged` signal is emitted when a phase starts or the session ends.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a signal could not be emitted.
+    pub fn notify(&self, event: &PhaseEvent) -> Result<()> {
+        let interface = self
+            .connection
+            .object_server()
+            .interface::<_, TimerInterface>(OBJECT_PATH)
+            .context("The timer object is not published.")?;
+        let ctxt = interface.signal_context();
+        zbus::block_on(async {
+            let timer = interface.get();
+            timer.phase_changed(ctxt).await?;
+            timer.counter_changed(ctxt).await?;
+            match event {
+                PhaseEvent::Started { phase, counter, .. } => {
+                    TimerInterface::emit_phase_changed(ctxt, &phase_name(Some(*phase)), *counter)
+                        .await
+                }
+                PhaseEvent::SessionEnded { counter } => {
+                    TimerInterface::emit_phase_changed(ctxt, "", *counter).await
+                }
+                PhaseEvent::Finished { .. } | PhaseEvent::Quit { .. } => Ok(()),
+            }
+        })
+        .context("Failed to emit the D-Bus signals.")
+    }
+}
+
+#[test]
+fn test_dbus_service() {
+    use std::io::BufRead;
+
+    /// Stops the private bus and removes its folder, even if an assertion fails.
+    struct PrivateBus {
+        folder: std::path::PathBuf,
+        daemon: Option<std::process::Child>,
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            if let Some(daemon) = &mut self.daemon {
+                let _ = daemon.kill();
+                let _ = daemon.wait();
+            }
+            let _ = std::fs::remove_dir_all(&self.folder);
+        }
+    }
+
+    // Start a private bus, so the test does not depend on the session bus.
+    let folder = std::env::temp_dir().join(format!("pomodoro_dbus_test_{}", std::process::id()));
+    std::fs::create_dir_all(&folder).unwrap();
+    let mut bus = PrivateBus {
+        folder: folder.clone(),
+        daemon: None,
+    };
+    let filepath_config = folder.join("bus.conf");
+    std::fs::write(
+        &filepath_config,
+        format!(
+            r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-Bus Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:path={}</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>"#,
+            folder.join("bus").display()
+        ),
+    )
+    .unwrap();
+    let daemon = bus.daemon.insert(
+        std::process::Command::new("dbus-daemon")
+            .arg(format!("--config-file={}", filepath_config.display()))
+            .args(["--nofork", "--print-address"])
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .expect("The D-Bus test requires dbus-daemon"),
+    );
+    let mut address = String::new();
+    std::io::BufReader::new(daemon.stdout.take().unwrap())
+        .read_line(&mut address)
+        .unwrap();
+    let address = address.trim();
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let service = DbusService::start(
+        Builder::address(address).unwrap(),
+        sender,
+        StatusHandle::default(),
+    )
+    .unwrap();
+    let client = Builder::address(address).unwrap().build().unwrap();
+    let proxy =
+        zbus::blocking::Proxy::new(&client, SERVICE_NAME, OBJECT_PATH, SERVICE_NAME).unwrap();
+    let mut phase_changes = proxy.receive_signal("PhaseChanged").unwrap();
+
+    for (method, input) in [
+        ("Pause", "pause"),
+        ("Resume", "resume"),
+        ("Skip", "skip 1"),
+        ("Stop", "quit"),
+    ] {
+        proxy.call_method(method, &()).unwrap();
+        assert_eq!(receiver.recv().unwrap(), input);
+    }
+    assert_eq!(proxy.get_property::<String>("Phase").unwrap(), "");
+    assert_eq!(proxy.get_property::<u64>("Remaining").unwrap(), 0);
+    assert_eq!(proxy.get_property::<i32>("Counter").unwrap(), 0);
+
+    service
+        .notify(&PhaseEvent::Started {
+            phase: Phase::ShortBreak,
+            duration: std::time::Duration::from_secs(300),
+            counter: 1,
+        })
+        .unwrap();
+    let signal = phase_changes.next().unwrap();
+    let (phase, counter): (String, i32) = signal.body().deserialize().unwrap();
+    assert_eq!(phase, "shortBreak");
+    assert_eq!(counter, 1);
+}
@@ -1,101 +1,685 @@
-/// This module defines the `EndEvent` enum and related functions for handling end events in the Pomodoro application.
-///
-/// The `EndEvent` enum represents different types of end events that can occur after a Pomodoro session, such as playing a sound or locking the screen.
-///
-/// # Examples
-///
-/// ```
-/// use pomodoro::end_events::{EndEvent, lock_screen, play_sound};
-/// use std::path::PathBuf;
-///
-/// let sound_event = EndEvent::Sound {
-///     filepath_sound: PathBuf::from("sound.wav"),
-/// };
-/// let screensaver_event = EndEvent::LockScreen;
-///
-/// // Play a sound
-/// if let EndEvent::Sound { filepath_sound } = sound_event {
-///     play_sound(&filepath_sound);
-/// }
-///
-/// // Lock the screen
-/// if let EndEvent::LockScreen = screensaver_event {
-///     lock_screen();
-/// }
-/// ```
-///
-/// # Note
-///
-/// - The `Sound` variant of `EndEvent` requires a file path to the sound file.
-/// - The `LockScreen` variant of `EndEvent` locks the screen.
-/// - The `lock_screen` function locks the screen on Windows using the `LockWorkStation` function from `user32.dll`.
-/// - The `play_sound` function plays a sound file using the `rodio` crate.
+//! This module defines the `EndEvent` enum and related functions for handling end events in the Pomodoro application.
+//!
+//! The `EndEvent` enum represents different types of end events that can occur after a Pomodoro session, such as playing a sound or locking the screen.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use locking_pomodoro_timer::end_events::{BuiltinSound, EndEvent, lock_screen, play_sound};
+//! use std::path::PathBuf;
+//!
+//! let sound_event = EndEvent::Sound {
+//!     filepath_sound: PathBuf::from("sound.wav"),
+//!     builtin: Some(BuiltinSound::Bell),
+//! };
+//! let screensaver_event = EndEvent::LockScreen;
+//!
+//! // Play a sound, falling back to the built-in bell if the file does not exist
+//! if let EndEvent::Sound { filepath_sound, builtin } = sound_event {
+//!     play_sound(&filepath_sound, builtin).unwrap();
+//! }
+//!
+//! // Lock the screen, detecting the screen locker on Linux
+//! if let EndEvent::LockScreen = screensaver_event {
+//!     lock_screen(&[]).unwrap();
+//! }
+//! ```
+//!
+//! # Note
+//!
+//! - The `Sound` variant of `EndEvent` plays a sound file or one of the built-in sounds of `BuiltinSound`.
+//! - The `LockScreen` variant of `EndEvent` locks the screen.
+//! - The `Notification` variant of `EndEvent` shows a desktop notification. Its summary and body are
+//!   templates which can contain the placeholders `{phase}`, `{next_phase}` and
+//!   `{pomodoros_till_long_break}`, which are filled from the `EndEventContext`.
+//! - The `Command` variant of `EndEvent` runs a program in the background. The context is passed in
+//!   environment variables like `POMODORO_PHASE`.
+//! - The `Webhook` variant of `EndEvent` sends the phase transition as JSON to an HTTP endpoint in the
+//!   background.
+//! - The `None` variant of `EndEvent` does nothing, e.g. to disable the end event of a phase.
+//! - The `Sequence` and `Parallel` variants of `EndEvent` combine several end events, which are
+//!   started in order or all at once. Together with the `Delay` variant, the screen can be locked
+//!   some time after a sound was played. Combined end events run in a background thread.
+//! - The `When` variant of `EndEvent` only starts its end event if the `Condition` is met.
+//! - A `TimedEvent` executes an `EndEvent` while a phase is running, e.g. one minute before its end.
+//! - The `lock_screen` function locks the screen on Windows using the `LockWorkStation` function from `user32.dll`
+//!   and on Linux using the screen lockers from the `screen_locker` module.
+//! - The `play_sound` function plays a sound file using the `rodio` crate. The built-in sounds are compiled into the binary.
+use crate::notification::{show_notification, Urgency};
+use crate::pomo_info::PomoInfo;
+use crate::pomodoro_options::PomodoroOptions;
+use crate::screen_locker::{lock_screen_on_linux, LockScreenError, ScreenLocker};
+use crate::session::Phase;
+use crate::view::View;
+use crate::webhook::{create_payload, send_webhook};
+use anyhow::{bail, Context, Result};
 use rodio::{Decoder, OutputStream, Sink};
-use serde;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use wait_timeout::ChildExt;
 
 /// Represents different types of end events that can occur after a Pomodoro session.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", rename_all_fields = "camelCase")]
-pub(crate) enum EndEvent {
+pub enum EndEvent {
     /// Play a sound specified by the file path.
-    Sound { filepath_sound: PathBuf },
+    /// If the file path is empty or does not exist, the built-in sound is played.
+    Sound {
+        /// The path to the sound file.
+        #[serde(default)]
+        filepath_sound: PathBuf,
+        /// The built-in sound to play if the sound file does not exist.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        builtin: Option<BuiltinSound>,
+    },
     /// Lock the screen.
     LockScreen,
+    /// Show a desktop notification.
+    ///
+    /// The summary and the body can contain the placeholders `{phase}`, `{next_phase}` and
+    /// `{pomodoros_till_long_break}`.
+    Notification {
+        /// The title of the notification.
+        #[serde(default = "default_notification_summary")]
+        summary: String,
+        /// The text of the notification.
+        #[serde(default = "default_notification_body")]
+        body: String,
+        /// The urgency level of the notification.
+        #[serde(default)]
+        urgency: Urgency,
+        /// The time in milliseconds after which the notification is closed.
+        /// If not set, the default of the notification server is used.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout: Option<u32>,
+    },
+    /// Run a program without waiting for it, e.g. to toggle Do Not Disturb or to pause the music.
+    ///
+    /// The program gets the environment variables `POMODORO_PHASE`, `POMODORO_NEXT_PHASE`,
+    /// `POMODORO_COUNTER`, `POMODORO_BREAK_MINUTES` and `POMODORO_LABEL`.
+    Command {
+        /// The program to run. It is searched in `PATH` if it is not a path.
+        program: String,
+        /// The arguments passed to the program.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+        /// The time in seconds after which the program is killed.
+        /// If not set, the program may run as long as it wants.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout: Option<u64>,
+    },
+    /// Send an HTTP request describing the phase transition, e.g. to a local aggregator.
+    ///
+    /// The request is sent in the background with a short timeout and retried if it fails.
+    Webhook {
+        /// The URL of the endpoint.
+        url: String,
+        /// The HTTP method.
+        #[serde(default = "default_webhook_method")]
+        method: String,
+        /// Additional headers of the request.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+        /// The template of the body, which can contain the same placeholders as a notification.
+        /// If not set, a JSON payload with the phase, the next phase, the counter, the Pomodoros
+        /// till the long break, the break minutes, the label and the time is sent.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        body_template: Option<String>,
+    },
+    /// Do nothing.
+    None,
+    /// Wait before the next end event of a `Sequence` is started.
+    Delay {
+        /// The time to wait in seconds.
+        seconds: f64,
+    },
+    /// Start the end events one after another.
+    Sequence(Vec<EndEvent>),
+    /// Start the end events at the same time.
+    Parallel(Vec<EndEvent>),
+    /// Start the end event only if the condition is met.
+    When {
+        /// The condition to check.
+        condition: Condition,
+        /// The end event to start if the condition is met.
+        event: Box<EndEvent>,
+    },
+}
+
+impl EndEvent {
+    /// Collects the paths of the sound files of the end event and all combined end events.
+    pub fn collect_sound_files<'a>(&'a self, filepaths_sound: &mut Vec<&'a Path>) {
+        match self {
+            EndEvent::Sound { filepath_sound, .. } => filepaths_sound.push(filepath_sound),
+            EndEvent::Sequence(end_events) | EndEvent::Parallel(end_events) => {
+                for end_event in end_events {
+                    end_event.collect_sound_files(filepaths_sound);
+                }
+            }
+            EndEvent::When { event, .. } => event.collect_sound_files(filepaths_sound),
+            _ => (),
+        }
+    }
+
+    /// Clears the paths of sound files which do not exist, so the built-in sound is played instead.
+    pub fn clear_missing_sound_files(&mut self) {
+        match self {
+            EndEvent::Sound { filepath_sound, .. } if !filepath_sound.is_file() => {
+                *filepath_sound = PathBuf::new();
+            }
+            EndEvent::Sequence(end_events) | EndEvent::Parallel(end_events) => {
+                for end_event in end_events {
+                    end_event.clear_missing_sound_files();
+                }
+            }
+            EndEvent::When { event, .. } => event.clear_missing_sound_files(),
+            _ => (),
+        }
+    }
+
+    /// Checks whether the end event is started in a background thread.
+    ///
+    /// Webhooks, combined end events and delays do not block the timer, so the next phase starts
+    /// while they are running.
+    pub fn runs_in_background(&self) -> bool {
+        match self {
+            EndEvent::Webhook { .. }
+            | EndEvent::Delay { .. }
+            | EndEvent::Sequence(_)
+            | EndEvent::Parallel(_) => true,
+            EndEvent::When { event, .. } => event.runs_in_background(),
+            _ => false,
+        }
+    }
+}
+
+/// Represents an event which is executed while a phase is running, e.g. a soft chime one minute
+/// before the Pomodoro ends.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimedEvent {
+    /// The phase during which the event is executed.
+    #[serde(default = "default_timed_event_phase")]
+    pub phase: Phase,
+    /// The time at which the event is executed.
+    pub at: EventTime,
+    /// The event to execute.
+    pub event: EndEvent,
+}
+
+/// Gets the phase of a timed event if none is specified.
+fn default_timed_event_phase() -> Phase {
+    Phase::Pomodoro
+}
+
+/// Represents the time during a phase at which a `TimedEvent` is executed.
+///
+/// The time is measured like the progress of the phase, so pauses delay the event and skipped
+/// minutes bring it forward.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EventTime {
+    /// The given number of seconds before the phase ends, e.g. `{"secondsBeforeEnd": 60}`.
+    SecondsBeforeEnd(u64),
+    /// The given fraction of the phase, e.g. `{"fraction": 0.5}` at the halfway mark.
+    Fraction(f64),
+}
+
+impl EventTime {
+    /// Checks whether the time is valid, i.e. a fraction is greater than 0 and less than 1.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            EventTime::SecondsBeforeEnd(_) => true,
+            EventTime::Fraction(fraction) => *fraction > 0.0 && *fraction < 1.0,
+        }
+    }
+
+    /// Gets the elapsed time of a phase with the given duration at which the event is due.
+    ///
+    /// Returns `None` if the time is not within the phase, e.g. 5 minutes before the end of a
+    /// phase of 3 minutes.
+    pub fn elapsed_time(&self, duration: Duration) -> Option<Duration> {
+        if !self.is_valid() {
+            return None;
+        }
+        let elapsed_time = match self {
+            EventTime::SecondsBeforeEnd(seconds) => {
+                duration.checked_sub(Duration::from_secs(*seconds))?
+            }
+            EventTime::Fraction(fraction) => duration.mul_f64(*fraction),
+        };
+        (!elapsed_time.is_zero()).then_some(elapsed_time)
+    }
+}
+
+/// Represents a condition of a `When` end event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Condition {
+    /// The next phase is the given phase, e.g. `{"nextPhase": "longBreak"}`.
+    NextPhase(Phase),
+    /// The break after the current or next Pomodoro is a long break.
+    LongBreakComing,
+    /// The number of Pomodoros till the long break equals the given number.
+    PomodorosTillLongBreak(i32),
+    /// The given condition is not met, e.g. `{"not": "longBreakComing"}`.
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    /// Checks whether the condition is met in the context.
+    pub fn is_met(&self, context: &EndEventContext) -> bool {
+        match self {
+            Condition::NextPhase(phase) => context.next_phase == *phase,
+            Condition::LongBreakComing => context.pomo_info.is_long_break_coming,
+            Condition::PomodorosTillLongBreak(pomodoros) => {
+                context.pomodoros_till_long_break() == *pomodoros
+            }
+            Condition::Not(condition) => !condition.is_met(context),
+        }
+    }
+}
+
+/// Gets the summary of a notification if none is specified.
+fn default_notification_summary() -> String {
+    "{phase} finished".to_string()
+}
+
+/// Gets the HTTP method of a webhook if none is specified.
+fn default_webhook_method() -> String {
+    "POST".to_string()
+}
+
+/// Gets the body of a notification if none is specified.
+fn default_notification_body() -> String {
+    "Next: {next_phase}".to_string()
+}
+
+/// Represents the situation in which an end event is started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndEventContext {
+    /// The phase which has finished.
+    pub phase: Phase,
+    /// The phase which follows.
+    pub next_phase: Phase,
+    /// The information about the Pomodoro of the next phase.
+    pub pomo_info: PomoInfo,
+    /// The number of completed Pomodoros.
+    pub counter: i32,
+    /// The label of the current task.
+    pub label: Option<String>,
+}
+
+impl EndEventContext {
+    /// Gets the number of Pomodoros which have to be finished until the next long break,
+    /// as shown in the status line of the next phase.
+    pub fn pomodoros_till_long_break(&self) -> i32 {
+        match self.next_phase {
+            Phase::Pomodoro | Phase::AdditionalPomodoro => self.pomo_info.pomodoros_till_long_break,
+            // The Pomodoro before the break is already finished.
+            Phase::ShortBreak | Phase::LongBreak => self.pomo_info.pomodoros_till_long_break - 1,
+        }
+    }
+
+    /// Replaces the placeholders `{phase}`, `{next_phase}` and `{pomodoros_till_long_break}`
+    /// in the template.
+    pub fn render(&self, template: &str) -> String {
+        template
+            .replace("{phase}", &self.phase.to_string())
+            .replace("{next_phase}", &self.next_phase.to_string())
+            .replace(
+                "{pomodoros_till_long_break}",
+                &self.pomodoros_till_long_break().to_string(),
+            )
+    }
+
+    /// Gets the environment variables passed to the program of a `Command` end event.
+    ///
+    /// The phases are named like in the options file, e.g. `shortBreak`.
+    pub fn environment(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("POMODORO_PHASE", phase_name(self.phase)),
+            ("POMODORO_NEXT_PHASE", phase_name(self.next_phase)),
+            ("POMODORO_COUNTER", self.counter.to_string()),
+            (
+                "POMODORO_BREAK_MINUTES",
+                (self.pomo_info.break_duration.as_secs() / 60).to_string(),
+            ),
+            ("POMODORO_LABEL", self.label.clone().unwrap_or_default()),
+        ]
+    }
+}
+
+/// Gets the name of the phase as it is serialized, e.g. `shortBreak`.
+fn phase_name(phase: Phase) -> String {
+    serde_json::to_value(phase)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Represents the sounds which are compiled into the binary.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BuiltinSound {
+    /// A repeated two-tone alarm. This is the default sound.
+    #[default]
+    Alarm,
+    /// A single bell strike.
+    Bell,
+    /// Three ascending chimes.
+    Chime,
+    /// Short beeps like a kitchen timer.
+    Digital,
+}
+
+impl BuiltinSound {
+    /// Gets the WAV data of the built-in sound.
+    fn wav_data(&self) -> &'static [u8] {
+        // include_bytes! macro is used to include the sound files in the binary.
+        match self {
+            BuiltinSound::Alarm => include_bytes!("../assets/sounds/alarm.wav"),
+            BuiltinSound::Bell => include_bytes!("../assets/sounds/bell.wav"),
+            BuiltinSound::Chime => include_bytes!("../assets/sounds/chime.wav"),
+            BuiltinSound::Digital => include_bytes!("../assets/sounds/digital.wav"),
+        }
+    }
 }
 
 /// Starts the specified end event.
-pub(crate) fn start_end_event(end_event: &EndEvent) {
-    match end_event {
-        EndEvent::Sound { filepath_sound } => play_sound(&filepath_sound),
-        EndEvent::LockScreen => lock_screen(),
+///
+/// Errors of the end event are logged and shown by the view, so that a failing end event does not stop the timer.
+/// End events which run in the background, e.g. a `Sequence`, are started in a separate thread.
+///
+/// # Arguments
+///
+/// * `end_event` - The end event to start.
+/// * `options` - The Pomodoro options, e.g. containing the screen lockers to use.
+/// * `context` - The finished phase and the phase which follows, e.g. to fill notification templates.
+/// * `view` - The view showing the errors, so they do not disturb e.g. the full-screen interface.
+pub fn start_end_event(
+    end_event: &EndEvent,
+    options: &PomodoroOptions,
+    context: &EndEventContext,
+    view: &Arc<dyn View>,
+) {
+    if end_event.runs_in_background() {
+        start_end_event_in_background(end_event, options, context, view);
+    } else {
+        run_end_event(end_event, options, context, view);
+    }
+}
+
+/// Starts the specified end event in a separate thread, so the caller is not blocked.
+///
+/// # Arguments
+///
+/// * `end_event` - The end event to start.
+/// * `options` - The Pomodoro options, e.g. containing the screen lockers to use.
+/// * `context` - The finished phase and the phase which follows, e.g. to fill notification templates.
+/// * `view` - The view showing the errors.
+pub fn start_end_event_in_background(
+    end_event: &EndEvent,
+    options: &PomodoroOptions,
+    context: &EndEventContext,
+    view: &Arc<dyn View>,
+) {
+    let end_event = end_event.clone();
+    let options = options.clone();
+    let context = context.clone();
+    let thread_view = view.clone();
+    let result = thread::Builder::new()
+        .name("end_event".to_string())
+        .spawn(move || run_end_event(&end_event, &options, &context, &thread_view));
+    if let Err(e) = result {
+        log::error!("Failed to spawn end event thread: {}", e);
+        view.show_message(&format!("Error: Failed to spawn end event thread: {}", e));
+    }
+}
+
+/// Runs the end event and waits for it, except for programs of `Command` end events.
+///
+/// Errors are logged and shown by the view. The remaining end events of a `Sequence` are started
+/// anyway.
+fn run_end_event(
+    end_event: &EndEvent,
+    options: &PomodoroOptions,
+    context: &EndEventContext,
+    view: &Arc<dyn View>,
+) {
+    let result = match end_event {
+        EndEvent::Sound {
+            filepath_sound,
+            builtin,
+        } => play_sound(filepath_sound, *builtin),
+        EndEvent::LockScreen => lock_screen(&options.screen_lockers),
+        EndEvent::Notification {
+            summary,
+            body,
+            urgency,
+            timeout,
+        } => show_notification(
+            &context.render(summary),
+            &context.render(body),
+            *urgency,
+            *timeout,
+        ),
+        EndEvent::Command {
+            program,
+            args,
+            timeout,
+        } => run_command(
+            program,
+            args,
+            timeout.map(Duration::from_secs),
+            context,
+            view,
+        ),
+        EndEvent::Webhook {
+            url,
+            method,
+            headers,
+            body_template,
+        } => {
+            let body = match body_template {
+                Some(body_template) => context.render(body_template),
+                None => create_payload(context),
+            };
+            send_webhook(url, method, headers, &body)
+        }
+        EndEvent::None => Ok(()),
+        EndEvent::Delay { seconds } => Duration::try_from_secs_f64(*seconds)
+            .map(thread::sleep)
+            .with_context(|| format!("Invalid delay of {} seconds.", seconds)),
+        EndEvent::Sequence(end_events) => {
+            for end_event in end_events {
+                run_end_event(end_event, options, context, view);
+            }
+            Ok(())
+        }
+        EndEvent::Parallel(end_events) => {
+            thread::scope(|scope| {
+                for end_event in end_events {
+                    scope.spawn(|| run_end_event(end_event, options, context, view));
+                }
+            });
+            Ok(())
+        }
+        EndEvent::When { condition, event } => {
+            if condition.is_met(context) {
+                run_end_event(event, options, context, view);
+            } else {
+                log::debug!("Skipping end event, because {:?} is not met.", condition);
+            }
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        log::error!("Failed to execute end event {:?}: {:#}", end_event, e);
+        view.show_message(&format!("Error: {:#}", e));
     }
 }
 
 /// Locks the screen.
-pub fn lock_screen() {
+///
+/// On Linux, the screen lockers are tried in the given order. If `screen_lockers` is empty,
+/// the order is detected from the desktop session.
+///
+/// # Errors
+///
+/// Returns an error if the screen could not be locked or if the platform is not supported.
+pub fn lock_screen(screen_lockers: &[ScreenLocker]) -> Result<()> {
     if cfg!(windows) {
-        lock_screen_on_windows();
+        lock_screen_on_windows()
+    } else if cfg!(target_os = "linux") {
+        lock_screen_on_linux(screen_lockers)
     } else {
-        // TODO: Implement for Linux and macOS.
-        todo!("This feature is not implemented for this platform.")
+        // TODO: Implement for macOS.
+        Err(LockScreenError::UnsupportedPlatform.into())
     }
 }
 
 /// Locks the screen on Windows.
-pub fn lock_screen_on_windows() {
+///
+/// # Errors
+///
+/// Returns an error if `LockWorkStation` could not be called.
+pub fn lock_screen_on_windows() -> Result<()> {
     // Turn on the screen saver for windows and lock the screen.
-    std::process::Command::new("cmd")
-        .args(&["/C", "rundll32", "user32.dll,LockWorkStation"])
+    let output = std::process::Command::new("cmd")
+        .args(["/C", "rundll32", "user32.dll,LockWorkStation"])
         .output()
-        .expect("Failed to start screen saver.");
+        .context("Failed to start screen saver.")?;
+    if !output.status.success() {
+        bail!("Failed to start screen saver: {}", output.status);
+    }
+    Ok(())
+}
+
+/// Runs the program in the background with the context in its environment.
+///
+/// The program is waited for in a separate thread, so the timer is not blocked. If it fails or
+/// runs longer than the timeout, it is killed and the failure is logged and shown by the view.
+///
+/// # Errors
+///
+/// Returns an error if the program could not be started.
+pub fn run_command(
+    program: &str,
+    args: &[String],
+    timeout: Option<Duration>,
+    context: &EndEventContext,
+    view: &Arc<dyn View>,
+) -> Result<()> {
+    let child = Command::new(program)
+        .args(args)
+        .envs(context.environment())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run `{}`.", program))?;
+    let program = program.to_string();
+    let view = view.clone();
+    thread::Builder::new()
+        .name("end_event_command".to_string())
+        .spawn(move || {
+            if let Err(e) = wait_for_command(child, timeout) {
+                log::error!("The end event command `{}` failed: {:#}", program, e);
+                view.show_message(&format!(
+                    "Error: The end event command `{}` failed: {:#}",
+                    program, e
+                ));
+            }
+        })
+        .context("Failed to spawn thread waiting for the command.")?;
+    Ok(())
+}
+
+/// Waits for the program to exit and kills it when the timeout is reached.
+///
+/// # Errors
+///
+/// Returns an error if the program exited with a non-zero status or was killed.
+fn wait_for_command(mut child: Child, timeout: Option<Duration>) -> Result<()> {
+    let status = match timeout {
+        None => child.wait()?,
+        Some(timeout) => match child.wait_timeout(timeout)? {
+            Some(status) => status,
+            None => {
+                child.kill()?;
+                child.wait()?;
+                bail!("Killed after the timeout of {:?}.", timeout);
+            }
+        },
+    };
+    if !status.success() {
+        bail!("Exited with {}.", status);
+    }
+    Ok(())
 }
 
 /// Plays the sound specified by the file path.
-pub fn play_sound(filepath_sound: &PathBuf) {
+///
+/// If the file does not exist, the built-in sound is played instead.
+/// If no built-in sound is specified, the default built-in sound is played.
+///
+/// # Errors
+///
+/// Returns an error if no audio output is available or if the sound file could not be decoded.
+pub fn play_sound(filepath_sound: &Path, builtin: Option<BuiltinSound>) -> Result<()> {
     let (_stream, stream_handle) =
-        OutputStream::try_default().expect("Failed to create output stream.");
-    let sink = Sink::try_new(&stream_handle).expect("Failed to create sink.");
+        OutputStream::try_default().context("Failed to create output stream.")?;
+    let sink = Sink::try_new(&stream_handle).context("Failed to create sink.")?;
 
     if filepath_sound.is_file() {
-        let sound_file = std::fs::File::open(filepath_sound).expect("Failed to open sound file.");
-        let source = Decoder::new(sound_file).expect("Failed to decode sound file.");
+        let sound_file =
+            std::fs::File::open(filepath_sound).context("Failed to open sound file.")?;
+        let source = Decoder::new(sound_file).context("Failed to decode sound file.")?;
         sink.append(source);
     } else {
-        // include_bytes! macro is used to include the sound file in the binary.
-        let sound_file = include_bytes!("C:/Windows/Media/Alarm01.wav");
-        let sound_cursor = std::io::Cursor::new(&sound_file[..]);
-        let source = Decoder::new(sound_cursor).unwrap();
+        let sound_cursor = std::io::Cursor::new(builtin.unwrap_or_default().wav_data());
+        let source = Decoder::new(sound_cursor).context("Failed to decode built-in sound.")?;
         sink.append(source);
     }
     sink.sleep_until_end();
+    Ok(())
+}
+
+/// Creates the view which shows the errors of the end events in the tests.
+#[cfg(test)]
+fn create_view() -> Arc<dyn View> {
+    Arc::new(crate::view::TerminalView::default())
+}
+
+/// Creates the context of an end event with the Pomodoro information derived from the options.
+#[cfg(test)]
+fn create_context(
+    options: &PomodoroOptions,
+    phase: Phase,
+    next_phase: Phase,
+    counter: i32,
+    label: Option<&str>,
+) -> EndEventContext {
+    EndEventContext {
+        phase,
+        next_phase,
+        pomo_info: PomoInfo::from_options(options, counter),
+        counter,
+        label: label.map(str::to_string),
+    }
 }
 
 #[test]
 fn test_serialize_end_event_to_json() {
     let sound_event = EndEvent::Sound {
         filepath_sound: PathBuf::from("sound.wav"),
+        builtin: None,
     };
     let screensaver_event = EndEvent::LockScreen;
 
@@ -108,3 +692,265 @@ fn test_serialize_end_event_to_json() {
     );
     assert_eq!(screensaver_event_json, r#""lockScreen""#);
 }
+
+#[test]
+fn test_deserialize_builtin_sound_from_json() {
+    let sound_event: EndEvent = serde_json::from_str(r#"{"sound":{"builtin":"bell"}}"#).unwrap();
+
+    match sound_event {
+        EndEvent::Sound {
+            filepath_sound,
+            builtin,
+        } => {
+            assert!(filepath_sound.as_os_str().is_empty());
+            assert_eq!(builtin, Some(BuiltinSound::Bell));
+        }
+        _ => panic!("Expected a sound event."),
+    }
+    for builtin in [
+        BuiltinSound::Alarm,
+        BuiltinSound::Bell,
+        BuiltinSound::Chime,
+        BuiltinSound::Digital,
+    ] {
+        assert!(Decoder::new(std::io::Cursor::new(builtin.wav_data())).is_ok());
+    }
+}
+
+#[test]
+fn test_notification_templates() {
+    let notification: EndEvent =
+        serde_json::from_str(r#"{"notification":{"urgency":"critical"}}"#).unwrap();
+    let EndEvent::Notification {
+        summary,
+        body,
+        urgency,
+        timeout,
+    } = notification
+    else {
+        panic!("Expected a notification event.");
+    };
+    assert_eq!(urgency, Urgency::Critical);
+    assert_eq!(timeout, None);
+
+    let options = PomodoroOptions {
+        interval_long_break: 4,
+        ..Default::default()
+    };
+    let context = create_context(&options, Phase::Pomodoro, Phase::ShortBreak, 1, None);
+    assert_eq!(context.render(&summary), "Pomodoro finished");
+    assert_eq!(context.render(&body), "Next: Short break");
+    assert_eq!(
+        context.render("{pomodoros_till_long_break} Pomodoros till long break"),
+        "2 Pomodoros till long break"
+    );
+    let context = create_context(&options, Phase::ShortBreak, Phase::Pomodoro, 2, None);
+    assert_eq!(
+        context.render("{phase} over, {pomodoros_till_long_break} left"),
+        "Short break over, 2 left"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_command_gets_context_in_environment() {
+    let filepath_output =
+        std::env::temp_dir().join(format!("pomodoro_command_test_{}.txt", std::process::id()));
+    let filepath_release =
+        std::env::temp_dir().join(format!("pomodoro_command_release_{}", std::process::id()));
+    // The program waits for the release file, which is only created after the call returned.
+    let script = format!(
+        "i=0; while [ ! -e {release} ] && [ $i -lt 100 ]; do sleep 0.05; i=$((i+1)); done; \
+         [ -e {release} ] || POMODORO_PHASE=blocked; \
+         echo $POMODORO_PHASE $POMODORO_NEXT_PHASE $POMODORO_COUNTER $POMODORO_BREAK_MINUTES $POMODORO_LABEL > {output}",
+        release = filepath_release.display(),
+        output = filepath_output.display(),
+    );
+    let end_event = EndEvent::Command {
+        program: "sh".to_string(),
+        args: vec!["-c".to_string(), script],
+        timeout: None,
+    };
+    let options = PomodoroOptions::default();
+    let context = create_context(
+        &options,
+        Phase::AdditionalPomodoro,
+        Phase::LongBreak,
+        3,
+        Some("Write report"),
+    );
+
+    start_end_event(&end_event, &options, &context, &create_view());
+    std::fs::write(&filepath_release, "").unwrap();
+    let start = std::time::Instant::now();
+    let mut output = String::new();
+    while !output.ends_with('\n') && start.elapsed() < Duration::from_secs(10) {
+        thread::sleep(Duration::from_millis(50));
+        output = std::fs::read_to_string(&filepath_output).unwrap_or_default();
+    }
+    std::fs::remove_file(&filepath_output).unwrap();
+    std::fs::remove_file(&filepath_release).unwrap();
+    assert_eq!(
+        output,
+        format!(
+            "additionalPomodoro longBreak 3 {} Write report\n",
+            options.duration_long_break
+        )
+    );
+
+    let sleeper = Command::new("sleep").arg("10").spawn().unwrap();
+    let error = wait_for_command(sleeper, Some(Duration::from_millis(100))).unwrap_err();
+    assert!(error.to_string().starts_with("Killed after the timeout"));
+    let failing = Command::new("false").spawn().unwrap();
+    assert!(wait_for_command(failing, None).is_err());
+}
+
+#[test]
+fn test_combined_end_events() {
+    let end_event: EndEvent = serde_json::from_str(
+        r#"{"sequence": [
+            {"sound": {}},
+            {"delay": {"seconds": 30}},
+            {"when": {"condition": {"nextPhase": "longBreak"}, "event": "lockScreen"}},
+            {"parallel": ["none", {"when": {"condition": {"not": "longBreakComing"}, "event": "none"}}]}
+        ]}"#,
+    )
+    .unwrap();
+    assert!(end_event.runs_in_background());
+    let EndEvent::Sequence(end_events) = &end_event else {
+        panic!("Expected a sequence.");
+    };
+    assert_eq!(end_events.len(), 4);
+    assert!(!end_events[0].runs_in_background());
+    assert!(end_events[1].runs_in_background());
+    assert!(!end_events[2].runs_in_background());
+    assert_eq!(
+        serde_json::to_string(&end_events[2]).unwrap(),
+        r#"{"when":{"condition":{"nextPhase":"longBreak"},"event":"lockScreen"}}"#
+    );
+
+    let options = PomodoroOptions {
+        interval_long_break: 2,
+        ..Default::default()
+    };
+    let context = create_context(
+        &options,
+        Phase::AdditionalPomodoro,
+        Phase::LongBreak,
+        1,
+        None,
+    );
+    assert!(Condition::NextPhase(Phase::LongBreak).is_met(&context));
+    assert!(Condition::LongBreakComing.is_met(&context));
+    assert!(Condition::PomodorosTillLongBreak(0).is_met(&context));
+    assert!(!Condition::Not(Box::new(Condition::LongBreakComing)).is_met(&context));
+    assert!(!Condition::NextPhase(Phase::ShortBreak).is_met(&context));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sequence_runs_in_background() {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let filepath_output =
+        std::env::temp_dir().join(format!("pomodoro_sequence_test_{}.txt", std::process::id()));
+    let end_event = EndEvent::Sequence(vec![
+        EndEvent::Delay { seconds: 0.3 },
+        serde_json::from_value(serde_json::json!({
+            "webhook": {"url": format!("http://{}/", server.server_addr().to_ip().unwrap())}
+        }))
+        .unwrap(),
+        EndEvent::When {
+            condition: Condition::NextPhase(Phase::ShortBreak),
+            event: Box::new(EndEvent::Command {
+                program: "touch".to_string(),
+                args: vec![filepath_output.display().to_string()],
+                timeout: None,
+            }),
+        },
+    ]);
+    let options = PomodoroOptions::default();
+    let context = create_context(&options, Phase::Pomodoro, Phase::ShortBreak, 0, None);
+
+    // The webhook is only answered after the call returned, so the sequence must run elsewhere.
+    let start = std::time::Instant::now();
+    start_end_event(&end_event, &options, &context, &create_view());
+    let request = server
+        .recv_timeout(Duration::from_secs(10))
+        .unwrap()
+        .unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert!(!filepath_output.exists());
+    request.respond(tiny_http::Response::empty(204)).unwrap();
+    assert!(server.try_recv().unwrap().is_none());
+    while !filepath_output.exists() && start.elapsed() < Duration::from_secs(10) {
+        thread::sleep(Duration::from_millis(50));
+    }
+    std::fs::remove_file(&filepath_output).unwrap();
+}
+
+#[test]
+fn test_event_time() {
+    let duration = Duration::from_secs(25 * 60);
+    assert_eq!(
+        EventTime::SecondsBeforeEnd(60).elapsed_time(duration),
+        Some(Duration::from_secs(24 * 60))
+    );
+    assert_eq!(
+        EventTime::Fraction(0.5).elapsed_time(duration),
+        Some(Duration::from_secs(750))
+    );
+    assert_eq!(
+        EventTime::SecondsBeforeEnd(25 * 60).elapsed_time(duration),
+        None
+    );
+    assert_eq!(
+        EventTime::SecondsBeforeEnd(30 * 60).elapsed_time(duration),
+        None
+    );
+    assert_eq!(EventTime::Fraction(1.0).elapsed_time(duration), None);
+    assert!(!EventTime::Fraction(-0.5).is_valid());
+
+    let timed_event: TimedEvent = serde_json::from_str(
+        r#"{"at": {"secondsBeforeEnd": 60}, "event": {"sound": {"builtin": "chime"}}}"#,
+    )
+    .unwrap();
+    assert_eq!(timed_event.phase, Phase::Pomodoro);
+    assert_eq!(timed_event.at, EventTime::SecondsBeforeEnd(60));
+}
+
+#[test]
+fn test_webhook_sends_payload() {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let end_event: EndEvent = serde_json::from_value(serde_json::json!({
+        "webhook": {"url": format!("http://{}/", server.server_addr().to_ip().unwrap())}
+    }))
+    .unwrap();
+    assert!(end_event.runs_in_background());
+    let options = PomodoroOptions::default();
+    let context = create_context(
+        &options,
+        Phase::Pomodoro,
+        Phase::ShortBreak,
+        0,
+        Some("Review"),
+    );
+
+    // The request is only answered after the call returned. If the call waited for the answer,
+    // the webhook would time out and be sent again.
+    start_end_event(&end_event, &options, &context, &create_view());
+    let mut request = server
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap()
+        .unwrap();
+    let mut body = String::new();
+    request.as_reader().read_to_string(&mut body).unwrap();
+    assert_eq!(request.method(), &tiny_http::Method::Post);
+    request.respond(tiny_http::Response::empty(204)).unwrap();
+    assert!(server.try_recv().unwrap().is_none());
+    let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(payload["phase"], "pomodoro");
+    assert_eq!(payload["nextPhase"], "shortBreak");
+    assert_eq!(payload["pomodorosTillLongBreak"], 3);
+    assert_eq!(payload["breakMinutes"], 5);
+    assert_eq!(payload["label"], "Review");
+}
@@ -0,0 +1,198 @@
+//! This module contains the session history which records every phase run by the timer.
+//!
+//! The history is stored as a JSON Lines file, i.e. every line is a JSON object describing one
+//! phase. New records are appended, so the file can be read while the timer is running.
+use crate::pomodoro_options::get_folderpath_executable;
+use crate::session::Phase;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Represents the record of a single phase in the history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRecord {
+    /// The wall-clock time when the phase started.
+    pub start: DateTime<Local>,
+    /// The wall-clock time when the phase ended or was quit.
+    pub end: DateTime<Local>,
+    /// The type of the phase.
+    pub phase: Phase,
+    /// The planned duration of the phase in seconds.
+    pub planned_seconds: u64,
+    /// The time actually spent in the phase in seconds, i.e. the wall-clock time without pauses.
+    pub actual_seconds: u64,
+    /// The time the timer was paused in seconds.
+    pub paused_seconds: u64,
+    /// The time skipped by pressing 's' in seconds.
+    pub skipped_seconds: u64,
+    /// Indicates whether the phase was quit by pressing 'q' before it ended.
+    pub quit: bool,
+    /// The label of the task worked on during the phase, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Gets the path to the history file.
+///
+/// If `filepath_history` is empty, the history file is located next to the executable.
+///
+/// # Errors
+///
+/// Returns an error if the folder of the executable could not be determined.
+pub fn get_filepath_history(filepath_history: &Path) -> Result<PathBuf> {
+    if !filepath_history.as_os_str().is_empty() {
+        return Ok(filepath_history.to_path_buf());
+    }
+    let mut path = get_folderpath_executable()?;
+    path.push("pomodoro_history.jsonl");
+    Ok(path)
+}
+
+/// Appends the record to the history file.
+///
+/// The history file and its parent folders are created if they do not exist.
+///
+/// # Errors
+///
+/// Returns an error if the history file could not be created or written.
+pub fn append_record(filepath_history: &Path, record: &HistoryRecord) -> Result<()> {
+    if let Some(folder) = filepath_history.parent() {
+        std::fs::create_dir_all(folder)
+            .with_context(|| format!("Failed to create folder: {:?}", folder))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(filepath_history)
+        .with_context(|| format!("Failed to open history file: {:?}", filepath_history))?;
+    let mut line = serde_json::to_string(record).context("Failed to serialize history record.")?;
+    line.push('\n');
+    // Write the line at once, so concurrent readers never see half a record.
+    file.write_all(line.as_bytes())
+        .with_context(|| format!("Failed to write to history file: {:?}", filepath_history))?;
+    Ok(())
+}
+
+/// Reads all records from the history file.
+///
+/// Lines which cannot be parsed, e.g. because the timer was killed while writing, are skipped.
+/// If the history file does not exist, no records are returned.
+///
+/// # Errors
+///
+/// Returns an error if the history file exists but could not be read.
+pub fn read_records(filepath_history: &Path) -> Result<Vec<HistoryRecord>> {
+    if !filepath_history.is_file() {
+        return Ok(Vec::new());
+    }
+    let contents = std::fs::read_to_string(filepath_history)
+        .with_context(|| format!("Failed to read history file: {:?}", filepath_history))?;
+    let mut records = Vec::new();
+    for (line_number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(record) => records.push(record),
+            Err(e) => warn!(
+                "Skipping line {} of history file {:?}: {}",
+                line_number + 1,
+                filepath_history,
+                e
+            ),
+        }
+    }
+    Ok(records)
+}
+
+/// Gets the most recently used task labels, starting with the latest one.
+///
+/// # Arguments
+///
+/// * `records` - The records of the history in chronological order.
+/// * `count` - The maximum number of labels to return.
+pub fn recent_labels(records: &[HistoryRecord], count: usize) -> Vec<String> {
+    let mut labels: Vec<String> = Vec::new();
+    for label in records
+        .iter()
+        .rev()
+        .filter_map(|record| record.label.as_ref())
+    {
+        if labels.len() == count {
+            break;
+        }
+        if !labels.contains(label) {
+            labels.push(label.clone());
+        }
+    }
+    labels
+}
+
+#[test]
+fn test_append_record() {
+    let filepath_history = std::env::temp_dir()
+        .join(format!("pomodoro_history_test_{}", std::process::id()))
+        .join("history.jsonl");
+    let start = Local::now();
+    let record = HistoryRecord {
+        start,
+        end: start + chrono::Duration::seconds(1560),
+        phase: Phase::Pomodoro,
+        planned_seconds: 1500,
+        actual_seconds: 1440,
+        paused_seconds: 120,
+        skipped_seconds: 60,
+        quit: false,
+        label: None,
+    };
+
+    append_record(&filepath_history, &record).unwrap();
+    append_record(&filepath_history, &record).unwrap();
+
+    let contents = std::fs::read_to_string(&filepath_history).unwrap();
+    let records = read_records(&filepath_history).unwrap();
+    std::fs::remove_dir_all(filepath_history.parent().unwrap()).unwrap();
+    assert_eq!(records, vec![record.clone(), record.clone()]);
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains(r#""phase":"pomodoro""#));
+    assert!(lines[0].contains(r#""pausedSeconds":120"#));
+    assert_eq!(
+        serde_json::from_str::<HistoryRecord>(lines[1]).unwrap(),
+        record
+    );
+}
+
+#[test]
+fn test_recent_labels() {
+    let start = Local::now();
+    let record = |label: Option<&str>| HistoryRecord {
+        start,
+        end: start,
+        phase: Phase::Pomodoro,
+        planned_seconds: 1500,
+        actual_seconds: 1500,
+        paused_seconds: 0,
+        skipped_seconds: 0,
+        quit: false,
+        label: label.map(str::to_string),
+    };
+    let records = vec![
+        record(Some("writing")),
+        record(Some("coding")),
+        record(None),
+        record(Some("review")),
+        record(Some("coding")),
+    ];
+    assert_eq!(
+        recent_labels(&records, 5),
+        vec!["coding", "review", "writing"]
+    );
+    assert_eq!(recent_labels(&records, 2), vec!["coding", "review"]);
+    assert!(recent_labels(&[], 5).is_empty());
+}
@@ -0,0 +1,261 @@
+//! This module contains the local HTTP API, which allows dashboards and other front ends to
+//! control the running timer.
+//!
+//! The server is bound to 127.0.0.1 on the port set with the option `httpPort` and answers with
+//! JSON. The following endpoints are available:
+//!
+//! - `GET /status`: The `SessionStatus` of the running timer.
+//! - `POST /pause`, `POST /resume`: Pause or resume the timer, like the keys `p` and `r` by default.
+//! - `POST /skip?minutes=N`: Skip N minutes, 1 minute by default and at most one day, like the
+//!   key `s` by default.
+//! - `POST /stop`: Quit the running timer, like the key `q` by default.
+//! - `GET /history?since=7d`: The records of the history, optionally since the given period.
+//!
+//! Web pages opened in a browser can send requests to 127.0.0.1 as well. Therefore requests with
+//! an `Origin` header, which browsers add to the requests of web pages, and requests for another
+//! `Host` than `127.0.0.1:PORT` or `localhost:PORT`, e.g. after DNS rebinding, are rejected.
+use crate::history::{read_records, HistoryRecord};
+use crate::key_bindings::MAX_MINUTES;
+use crate::session::StatusHandle;
+use crate::stats::parse_since;
+use anyhow::{anyhow, Context, Result};
+use chrono::Local;
+use log::{debug, warn};
+use serde::Serialize;
+use serde_json::json;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Starts the HTTP API on 127.0.0.1.
+///
+/// Requests are handled in a separate thread. Actions are sent as input through the `sender`,
+/// so they are handled exactly like the keys pressed in the terminal.
+///
+/// # Arguments
+///
+/// * `port` - The port to listen on. If 0, a free port is chosen.
+/// * `sender` - The sender of the input channel of the session.
+/// * `status` - The handle to query the status of the session.
+/// * `filepath_history` - The path to the history file.
+///
+/// # Returns
+///
+/// The address the server is listening on.
+///
+/// # Errors
+///
+/// Returns an error if the server could not be started, e.g. because the port is in use.
+pub fn start_http_server(
+    port: u16,
+    sender: Sender<String>,
+    status: StatusHandle,
+    filepath_history: PathBuf,
+) -> Result<SocketAddr> {
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|e| anyhow!(e))
+        .with_context(|| format!("Failed to start the HTTP API on port {}.", port))?;
+    let address = server
+        .server_addr()
+        .to_ip()
+        .context("The HTTP API is not listening on an IP address.")?;
+    std::thread::Builder::new()
+        .name("http_api".to_string())
+        .spawn(move || {
+            for request in server.incoming_requests() {
+                handle_request(request, address.port(), &sender, &status, &filepath_history);
+            }
+        })
+        .context("Failed to spawn HTTP API thread.")?;
+    Ok(address)
+}
+
+/// Handles a single request and sends the response.
+fn handle_request(
+    request: Request,
+    port: u16,
+    sender: &Sender<String>,
+    status: &StatusHandle,
+    filepath_history: &Path,
+) {
+    debug!(
+        "Received HTTP request: {} {}",
+        request.method(),
+        request.url()
+    );
+    let (path, query) = match request.url().split_once('?') {
+        Some((path, query)) => (path.to_string(), query.to_string()),
+        None => (request.url().to_string(), String::new()),
+    };
+    let (status_code, body) = match (request.method(), path.as_str()) {
+        _ if !is_local_request(&request, port) => {
+            warn!("Rejected HTTP request which was not sent by a local program.");
+            error(403, "Requests from web pages are not allowed.".to_string())
+        }
+        (Method::Get, "/status") => (200, to_json(&status.status())),
+        (Method::Post, "/pause") => send_input(sender, "pause".to_string()),
+        (Method::Post, "/resume") => send_input(sender, "resume".to_string()),
+        (Method::Post, "/skip") => match get_query_parameter(&query, "minutes") {
+            None => send_input(sender, "skip 1".to_string()),
+            Some(minutes) => match minutes.parse::<u64>() {
+                Ok(minutes) if minutes <= MAX_MINUTES => {
+                    send_input(sender, format!("skip {}", minutes))
+                }
+                Ok(_) => error(
+                    400,
+                    format!("At most {} minutes can be skipped.", MAX_MINUTES),
+                ),
+                Err(_) => error(400, format!("Invalid number of minutes: {:?}", minutes)),
+            },
+        },
+        (Method::Post, "/stop") => send_input(sender, "quit".to_string()),
+        (Method::Get, "/history") => match get_history(filepath_history, &query) {
+            Ok(records) => (200, to_json(&records)),
+            Err(e) => error(400, format!("{:#}", e)),
+        },
+        (_, "/status" | "/pause" | "/resume" | "/skip" | "/stop" | "/history") => {
+            error(405, "Method not allowed.".to_string())
+        }
+        _ => error(404, "Not found.".to_string()),
+    };
+    let response = Response::from_string(body)
+        .with_status_code(status_code)
+        .with_header(
+            Header::from_bytes("Content-Type", "application/json").expect("The header is valid."),
+        );
+    if let Err(e) = request.respond(response) {
+        warn!("Failed to answer HTTP request: {}", e);
+    }
+}
+
+/// Checks that the request is addressed to the API on this computer and not sent by a web page.
+fn is_local_request(request: &Request, port: u16) -> bool {
+    let header = |name: &'static str| {
+        request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv(name))
+            .map(|header| header.value.as_str())
+    };
+    let hosts = [format!("127.0.0.1:{}", port), format!("localhost:{}", port)];
+    header("Origin").is_none()
+        && header("Host").is_some_and(|host| hosts.iter().any(|allowed| allowed == host))
+}
+
+/// Sends the input to the session.
+fn send_input(sender: &Sender<String>, input: String) -> (u16, String) {
+    match sender.send(input) {
+        Ok(()) => (200, json!({ "ok": true }).to_string()),
+        Err(_) => error(503, "The session has ended.".to_string()),
+    }
+}
+
+/// Creates an error response.
+fn error(status_code: u16, message: String) -> (u16, String) {
+    (
+        status_code,
+        json!({ "ok": false, "error": message }).to_string(),
+    )
+}
+
+/// Serializes the value to JSON.
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("Failed to serialize response.")
+}
+
+/// Gets the value of a parameter of the query string.
+fn get_query_parameter<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|parameter| parameter.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Reads the records of the history, optionally filtered by the `since` parameter.
+fn get_history(filepath_history: &Path, query: &str) -> Result<Vec<HistoryRecord>> {
+    let records = read_records(filepath_history)?;
+    match get_query_parameter(query, "since") {
+        Some(since) => {
+            let since = parse_since(since, Local::now())?;
+            Ok(records
+                .into_iter()
+                .filter(|record| record.start >= since)
+                .collect())
+        }
+        None => Ok(records),
+    }
+}
+
+#[test]
+fn test_http_api() {
+    use std::io::{Read, Write};
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let filepath_history = std::env::temp_dir().join(format!(
+        "pomodoro_http_api_test_{}.jsonl",
+        std::process::id()
+    ));
+    let address = start_http_server(0, sender, StatusHandle::default(), filepath_history).unwrap();
+    let request_with_headers = |method: &str, path: &str, headers: &str| {
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\n{}Connection: close\r\nContent-Length: 0\r\n\r\n",
+            method, path, headers
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status_code: u16 = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status_code, body.to_string())
+    };
+    let host = format!("Host: localhost:{}\r\n", address.port());
+    let request = |method: &str, path: &str| request_with_headers(method, path, &host);
+
+    assert_eq!(
+        request("POST", "/pause"),
+        (200, r#"{"ok":true}"#.to_string())
+    );
+    assert_eq!(receiver.recv().unwrap(), "pause");
+    assert_eq!(request("POST", "/resume").0, 200);
+    assert_eq!(receiver.recv().unwrap(), "resume");
+    assert_eq!(request("POST", "/skip").0, 200);
+    assert_eq!(receiver.recv().unwrap(), "skip 1");
+    assert_eq!(request("POST", "/skip?minutes=5").0, 200);
+    assert_eq!(receiver.recv().unwrap(), "skip 5");
+    assert_eq!(request("POST", "/skip?minutes=five").0, 400);
+    let path = format!("/skip?minutes={}", MAX_MINUTES);
+    assert_eq!(request("POST", &path).0, 200);
+    assert_eq!(receiver.recv().unwrap(), format!("skip {}", MAX_MINUTES));
+    let path = format!("/skip?minutes={}", MAX_MINUTES + 1);
+    assert_eq!(request("POST", &path).0, 400);
+    let path = format!("/skip?minutes={}", u64::MAX);
+    assert_eq!(request("POST", &path).0, 400);
+    assert_eq!(request("POST", "/stop").0, 200);
+    assert_eq!(receiver.recv().unwrap(), "quit");
+
+    let (status_code, body) = request("GET", "/status");
+    assert_eq!(status_code, 200);
+    assert!(body.contains(r#""phase":null"#));
+    assert_eq!(request("GET", "/history"), (200, "[]".to_string()));
+    assert_eq!(request("GET", "/history?since=tomorrow").0, 400);
+    assert_eq!(request("GET", "/history?since=-7d").0, 400);
+    assert_eq!(request("GET", "/pause").0, 405);
+    assert_eq!(request("GET", "/unknown").0, 404);
+
+    // Requests of web pages are rejected before they reach the session.
+    let local_host = format!("Host: 127.0.0.1:{}\r\n", address.port());
+    assert_eq!(request_with_headers("GET", "/status", &local_host).0, 200);
+    let origin = format!("{}Origin: https://example.com\r\n", host);
+    assert_eq!(request_with_headers("POST", "/stop", &origin).0, 403);
+    let rebound_host = format!("Host: attacker.example:{}\r\n", address.port());
+    assert_eq!(
+        request_with_headers("GET", "/history", &rebound_host).0,
+        403
+    );
+    assert_eq!(request_with_headers("POST", "/pause", "").0, 403);
+    assert!(receiver.try_recv().is_err());
+}
@@ -1,6 +1,8 @@
+//! This module reads the key events of the terminal and sends them through a channel.
 use crossterm::event::read;
 use crossterm::event::Event;
 use crossterm::event::KeyCode;
+use crossterm::event::KeyEventKind;
 use crossterm::terminal::disable_raw_mode;
 use crossterm::terminal::enable_raw_mode;
 use log::debug;
@@ -8,9 +10,11 @@ use log::trace;
 
 /// Creates a channel to communicate key events from the terminal to the main thread.
 /// 
-/// Not all key events are sent through the channel. Only printable characters and the enter key.
+/// Not all key events are sent through the channel. Only printable characters, the enter key and
+/// the special keys which can be bound to actions, see `key_bindings::Key`. The enter key is sent
+/// as "\n", the other special keys by their name, e.g. "backspace", "esc" or "left".
 /// The ctrl+c key combination is used to exit the program and also sent through the channel.
-/// Only the key press events are sent through the channel. The key release events are ignored.
+/// Every key is only sent once, although some platforms report its press and its release.
 ///
 /// # Returns
 ///
@@ -21,8 +25,25 @@ use log::trace;
 /// This function will panic if the underlying operating system is unable to create the channel or
 /// if the input thread is unable to start or if the terminal is unable to enter raw mode.
 ///
-pub(crate) fn start_input_stream() -> std::sync::mpsc::Receiver<String> {
+pub fn start_input_stream() -> std::sync::mpsc::Receiver<String> {
     let (sender, receiver) = std::sync::mpsc::channel::<String>();
+    spawn_input_thread(sender);
+    receiver
+}
+
+/// Spawns the thread which sends the key events of the terminal through the given channel.
+///
+/// This allows other sources, e.g. the control socket, to send input through the same channel.
+///
+/// # Arguments
+///
+/// * `sender` - The sender of the channel the key events are sent through.
+///
+/// # Panics
+///
+/// This function will panic if the input thread is unable to start or if the terminal is unable
+/// to enter raw mode.
+pub fn spawn_input_thread(sender: std::sync::mpsc::Sender<String>) {
     std::thread::Builder::new()
         .name("input_stream".to_string())
         .spawn(move || {
@@ -36,14 +57,13 @@ pub(crate) fn start_input_stream() -> std::sync::mpsc::Receiver<String> {
             }
         })
         .expect("Failed to spawn input thread.");
-    receiver
 }
 
 /// Processes the key events received from the terminal.
 /// 
 /// This function reads key events from the terminal using the `crossterm` library.
-/// It checks if the key event is a press event and filters out key release events.
-/// If the key event is a printable character or the enter key, it sends the corresponding
+/// It filters out the duplicated key events, see `is_sent_key_event_kind`.
+/// If the key event is a printable character, the enter key or another special key, it sends the corresponding
 /// string representation through the channel to the main thread.
 /// If the key event is the ctrl+c combination, it sends "ctrl+c" through the channel to
 /// indicate that the program should exit.
@@ -55,12 +75,21 @@ pub(crate) fn start_input_stream() -> std::sync::mpsc::Receiver<String> {
 /// # Returns
 ///
 /// A boolean value indicating whether the program should exit or not.
+/// The input thread also exits if the terminal can not be read anymore, so it does not spin.
 ///
 fn process_key_event(sender: &std::sync::mpsc::Sender<String>) -> bool {
     let mut exit = false;
-    if let Ok(Event::Key(key_event)) = read() {
+    let event = match read() {
+        Ok(event) => event,
+        Err(e) => {
+            log::error!("Failed to read from the terminal: {}", e);
+            let _ = disable_raw_mode();
+            return true;
+        }
+    };
+    if let Event::Key(key_event) = event {
         debug!("Received key event: {:?}", key_event);
-        if key_event.kind != crossterm::event::KeyEventKind::Press {
+        if is_sent_key_event_kind(key_event.kind) {
             if key_event.code == KeyCode::Char('c')
                 && key_event.modifiers == crossterm::event::KeyModifiers::CONTROL
             {
@@ -76,8 +105,38 @@ fn process_key_event(sender: &std::sync::mpsc::Sender<String>) -> bool {
                 sender
                     .send("\n".to_string())
                     .expect("Failed to send input.");
+            } else if let Some(name) = special_key_name(key_event.code) {
+                sender
+                    .send(name.to_string())
+                    .expect("Failed to send input.");
             }
         }
     }
     exit
 }
+
+/// Gets the name of the special key which is sent through the channel, if it is sent at all.
+fn special_key_name(code: KeyCode) -> Option<&'static str> {
+    match code {
+        KeyCode::Backspace => Some("backspace"),
+        KeyCode::Esc => Some("esc"),
+        KeyCode::Tab => Some("tab"),
+        KeyCode::Left => Some("left"),
+        KeyCode::Right => Some("right"),
+        KeyCode::Up => Some("up"),
+        KeyCode::Down => Some("down"),
+        _ => None,
+    }
+}
+
+/// Checks whether key events of this kind are sent through the channel.
+///
+/// Windows reports the press and the release of a key, so only the release is sent to not handle
+/// a key twice. Other platforms only report the press of a key.
+fn is_sent_key_event_kind(kind: KeyEventKind) -> bool {
+    if cfg!(windows) {
+        kind != KeyEventKind::Press
+    } else {
+        kind == KeyEventKind::Press
+    }
+}
@@ -0,0 +1,405 @@
+//! This module contains the `KeyBindings` which map the keys of the terminal to the actions of
+//! the timer.
+//!
+//! The input of a session is either a key sent by the `input_handler` or a command sent by
+//! another interface, e.g. the HTTP API. The commands `pause`, `resume`, `skip N`, `quit` and
+//! `confirm` are not affected by the key bindings, so the remote interfaces keep working when the
+//! keys are changed.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The largest number of minutes a phase can be skipped or extended by at once, one day.
+pub const MAX_MINUTES: u64 = 24 * 60;
+
+/// Represents a key of the terminal which can be bound to an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Key {
+    /// A printable character, e.g. `p`.
+    Char(char),
+    /// The space bar.
+    Space,
+    /// The enter key.
+    Enter,
+    /// The escape key.
+    Esc,
+    /// The tab key.
+    Tab,
+    /// The backspace key.
+    Backspace,
+    /// The left arrow key.
+    Left,
+    /// The right arrow key.
+    Right,
+    /// The up arrow key.
+    Up,
+    /// The down arrow key.
+    Down,
+    /// The ctrl+c key combination.
+    CtrlC,
+}
+
+/// Error type for keys which can not be parsed.
+#[derive(Error, Debug, PartialEq)]
+#[error("Invalid key: {0:?}. Use a single character or space, enter, esc, tab, backspace, left, right, up, down or ctrl+c.")]
+pub struct InvalidKeyError(String);
+
+impl Key {
+    /// Gets the key from the input sent by the `input_handler`.
+    ///
+    /// # Returns
+    ///
+    /// The key or `None` if the input is not a key, e.g. a command of another interface.
+    pub fn from_input(input: &str) -> Option<Key> {
+        match input {
+            " " => Some(Key::Space),
+            "\n" => Some(Key::Enter),
+            _ => {
+                let mut chars = input.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some(Key::Char(c)),
+                    _ => Self::from_name(input),
+                }
+            }
+        }
+    }
+
+    /// Gets the special key with the name, ignoring the case.
+    fn from_name(name: &str) -> Option<Key> {
+        let key = match name.to_lowercase().as_str() {
+            "space" => Key::Space,
+            "enter" => Key::Enter,
+            "esc" | "escape" => Key::Esc,
+            "tab" => Key::Tab,
+            "backspace" => Key::Backspace,
+            "left" => Key::Left,
+            "right" => Key::Right,
+            "up" => Key::Up,
+            "down" => Key::Down,
+            "ctrl+c" => Key::CtrlC,
+            _ => return None,
+        };
+        Some(key)
+    }
+
+    /// Gets the key as shown in the help text, e.g. `'p'` or `space`.
+    pub fn label(&self) -> String {
+        match self {
+            Key::Char(c) => format!("'{}'", c),
+            key => key.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Key::Char(c) => return write!(f, "{}", c),
+            Key::Space => "space",
+            Key::Enter => "enter",
+            Key::Esc => "esc",
+            Key::Tab => "tab",
+            Key::Backspace => "backspace",
+            Key::Left => "left",
+            Key::Right => "right",
+            Key::Up => "up",
+            Key::Down => "down",
+            Key::CtrlC => "ctrl+c",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Key {
+    type Err = InvalidKeyError;
+
+    /// Parses a single character, e.g. `p`, or the name of a special key, e.g. `Space`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(' '), None) => Ok(Key::Space),
+            (Some(c), None) => Ok(Key::Char(c)),
+            _ => Self::from_name(s).ok_or_else(|| InvalidKeyError(s.to_string())),
+        }
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = InvalidKeyError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> Self {
+        key.to_string()
+    }
+}
+
+/// Represents an action of the timer triggered by a key or a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Pauses the running timer or resumes the paused timer.
+    TogglePause,
+    /// Pauses the timer.
+    Pause,
+    /// Resumes the timer.
+    Resume,
+    /// Skips the given number of minutes.
+    Skip(u64),
+    /// Quits the current phase without executing its end event.
+    QuitPhase,
+    /// Exits the program.
+    QuitApp,
+    /// Extends the current phase by the given number of minutes.
+    Extend(u64),
+    /// Ends the current phase as if its time was up.
+    NextPhase,
+    /// Changes the label of the task.
+    ChangeLabel,
+    /// Confirms a question, e.g. whether to start the next Pomodoro.
+    Confirm,
+}
+
+/// Maps the keys of the terminal to the actions of the timer.
+///
+/// Every action can be bound to several keys. Actions which are not specified in the options file
+/// keep their default keys.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default = "KeyBindings::default")]
+pub struct KeyBindings {
+    /// The keys which pause the running timer or resume the paused timer.
+    pub toggle_pause: Vec<Key>,
+    /// The keys which pause the timer.
+    pub pause: Vec<Key>,
+    /// The keys which resume the timer.
+    pub resume: Vec<Key>,
+    /// The keys which skip `skip_minutes` of the timer.
+    pub skip: Vec<Key>,
+    /// The number of minutes skipped by the `skip` keys, at most `MAX_MINUTES`.
+    pub skip_minutes: u64,
+    /// The keys which quit the current phase without executing its end event.
+    pub quit_phase: Vec<Key>,
+    /// The keys which exit the program. The ctrl+c key combination always exits the program.
+    pub quit_app: Vec<Key>,
+    /// The keys which extend the current phase by `extend_minutes`.
+    pub extend: Vec<Key>,
+    /// The number of minutes the `extend` keys add to the current phase, at most `MAX_MINUTES`.
+    pub extend_minutes: u64,
+    /// The keys which end the current phase as if its time was up.
+    pub next_phase: Vec<Key>,
+    /// The keys which change the label of the task.
+    pub change_label: Vec<Key>,
+    /// The keys which confirm a question, e.g. whether to start the next Pomodoro.
+    pub confirm: Vec<Key>,
+}
+
+impl Default for KeyBindings {
+    /// Creates the default key bindings, which include the keys of earlier versions.
+    fn default() -> Self {
+        KeyBindings {
+            toggle_pause: vec![Key::Space],
+            pause: vec![Key::Char('p')],
+            resume: vec![Key::Char('r')],
+            skip: vec![Key::Char('s')],
+            skip_minutes: 1,
+            quit_phase: vec![Key::Char('q')],
+            quit_app: vec![Key::CtrlC],
+            extend: vec![Key::Char('e')],
+            extend_minutes: 5,
+            next_phase: vec![Key::Char('n')],
+            change_label: vec![Key::Char('l')],
+            confirm: vec![Key::Enter],
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Gets the action of a command sent by another interface, e.g. `skip 5`.
+    ///
+    /// # Returns
+    ///
+    /// The action or `None` if the input is not a command, e.g. a key.
+    pub fn command(input: &str) -> Option<Action> {
+        match input {
+            "pause" => Some(Action::Pause),
+            "resume" => Some(Action::Resume),
+            "quit" => Some(Action::QuitPhase),
+            "confirm" => Some(Action::Confirm),
+            _ => input
+                .strip_prefix("skip ")
+                .and_then(|minutes| minutes.parse().ok())
+                .filter(|minutes| *minutes <= MAX_MINUTES)
+                .map(Action::Skip),
+        }
+    }
+
+    /// Gets the action of the input.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - A key sent by the `input_handler` or a command sent by another interface.
+    ///
+    /// # Returns
+    ///
+    /// The action or `None` if the input is neither a command nor a bound key.
+    pub fn action(&self, input: &str) -> Option<Action> {
+        let command = Self::command(input);
+        if command.is_some() {
+            return command;
+        }
+        let key = Key::from_input(input)?;
+        let actions = [
+            Action::TogglePause,
+            Action::Pause,
+            Action::Resume,
+            Action::Skip(self.skip_minutes),
+            Action::QuitPhase,
+            Action::QuitApp,
+            Action::Extend(self.extend_minutes),
+            Action::NextPhase,
+            Action::ChangeLabel,
+            Action::Confirm,
+        ];
+        let action = actions
+            .into_iter()
+            .find(|action| self.keys(action).contains(&key));
+        match action {
+            None if key == Key::CtrlC => Some(Action::QuitApp),
+            action => action,
+        }
+    }
+
+    /// Gets the keys bound to the action.
+    pub fn keys(&self, action: &Action) -> &[Key] {
+        match action {
+            Action::TogglePause => &self.toggle_pause,
+            Action::Pause => &self.pause,
+            Action::Resume => &self.resume,
+            Action::Skip(_) => &self.skip,
+            Action::QuitPhase => &self.quit_phase,
+            Action::QuitApp => &self.quit_app,
+            Action::Extend(_) => &self.extend,
+            Action::NextPhase => &self.next_phase,
+            Action::ChangeLabel => &self.change_label,
+            Action::Confirm => &self.confirm,
+        }
+    }
+
+    /// Checks that no key is bound to more than one action.
+    pub fn is_valid(&self) -> bool {
+        let mut keys: Vec<&Key> = [
+            &self.toggle_pause,
+            &self.pause,
+            &self.resume,
+            &self.skip,
+            &self.quit_phase,
+            &self.quit_app,
+            &self.extend,
+            &self.next_phase,
+            &self.change_label,
+            &self.confirm,
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let count = keys.len();
+        keys.sort_by_key(|key| key.to_string());
+        keys.dedup();
+        keys.len() == count
+    }
+
+    /// Generates the help text for the actions from their keys.
+    ///
+    /// Actions with the same description are listed together and actions without keys are left
+    /// out.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - The actions with their descriptions, e.g. `(Action::Pause, "pause")`.
+    ///
+    /// # Returns
+    ///
+    /// The help text, e.g. `'p' or space to pause and 'q' to quit`.
+    pub fn help(&self, entries: &[(Action, &str)]) -> String {
+        let mut descriptions: Vec<(&str, Vec<String>)> = Vec::new();
+        for (action, description) in entries {
+            let labels = self.keys(action).iter().map(Key::label);
+            match descriptions.iter_mut().find(|(d, _)| d == description) {
+                Some((_, keys)) => keys.extend(labels),
+                None => descriptions.push((description, labels.collect())),
+            }
+        }
+        let parts: Vec<String> = descriptions
+            .into_iter()
+            .filter(|(_, keys)| !keys.is_empty())
+            .map(|(description, keys)| format!("{} to {}", keys.join(" or "), description))
+            .collect();
+        match parts.split_last() {
+            Some((last, [])) => last.clone(),
+            Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+            None => String::new(),
+        }
+    }
+}
+
+/// Describes skipping or extending by the number of minutes, e.g. `skip 1 minute`.
+pub fn describe_minutes(verb: &str, minutes: u64) -> String {
+    if minutes == 1 {
+        format!("{} 1 minute", verb)
+    } else {
+        format!("{} {} minutes", verb, minutes)
+    }
+}
+
+#[test]
+fn test_key_bindings() {
+    let key_bindings: KeyBindings = serde_json::from_str(
+        r#"{"togglePause": ["Space", "p"], "pause": [], "skip": ["right"], "skipMinutes": 5}"#,
+    )
+    .unwrap();
+    assert!(key_bindings.is_valid());
+    assert_eq!(key_bindings.action(" "), Some(Action::TogglePause));
+    assert_eq!(key_bindings.action("p"), Some(Action::TogglePause));
+    assert_eq!(key_bindings.action("right"), Some(Action::Skip(5)));
+    assert_eq!(key_bindings.action("s"), None);
+    assert_eq!(key_bindings.action("\n"), Some(Action::Confirm));
+    assert_eq!(key_bindings.action("ctrl+c"), Some(Action::QuitApp));
+    // The commands of the other interfaces do not depend on the keys.
+    assert_eq!(key_bindings.action("pause"), Some(Action::Pause));
+    assert_eq!(key_bindings.action("skip 2"), Some(Action::Skip(2)));
+    assert_eq!(key_bindings.action("skip two"), None);
+    assert_eq!(KeyBindings::command("resume"), Some(Action::Resume));
+    assert_eq!(KeyBindings::command("p"), None);
+    assert_eq!(
+        key_bindings.action(&format!("skip {}", MAX_MINUTES)),
+        Some(Action::Skip(MAX_MINUTES))
+    );
+    assert_eq!(
+        key_bindings.action(&format!("skip {}", MAX_MINUTES + 1)),
+        None
+    );
+    assert_eq!(key_bindings.action(&format!("skip {}", u64::MAX)), None);
+
+    assert_eq!(
+        key_bindings.help(&[
+            (Action::TogglePause, "pause"),
+            (Action::Pause, "pause"),
+            (Action::Skip(5), "skip 5 minutes"),
+            (Action::QuitPhase, "quit"),
+        ]),
+        "space or 'p' to pause, right to skip 5 minutes and 'q' to quit"
+    );
+    assert_eq!("Esc".parse::<Key>(), Ok(Key::Esc));
+    assert!("pageup".parse::<Key>().is_err());
+
+    let key_bindings = KeyBindings {
+        quit_app: vec![Key::CtrlC, Key::Char('q')],
+        ..KeyBindings::default()
+    };
+    assert!(!key_bindings.is_valid());
+}
@@ -0,0 +1,35 @@
+#![warn(missing_docs)]
+#![doc = include_str!("../README.md")]
+pub(crate) mod cli_utilities;
+pub mod clock;
+#[cfg(target_os = "linux")]
+pub mod dbus_service;
+#[cfg(unix)]
+pub mod control;
+pub mod end_events;
+pub mod history;
+pub mod http_api;
+pub(crate) mod input_handler;
+pub mod key_bindings;
+mod message_creator;
+pub mod notification;
+pub mod persistence;
+pub mod pomo_info;
+pub mod pomodoro_options;
+pub mod screen_locker;
+pub mod session;
+pub mod simulation;
+pub mod state_machine;
+pub mod stats;
+pub mod timer;
+mod toml_document;
+pub(crate) mod tui;
+pub mod view;
+pub mod webhook;
+
+pub use cli_utilities::start_pomodoro;
+pub use end_events::EndEvent;
+pub use input_handler::start_input_stream;
+pub use pomo_info::PomoInfo;
+pub use session::{Phase, PhaseEvent, PomodoroSession, SessionOutcome};
+pub use timer::Timer;
@@ -1,36 +1,284 @@
 #![warn(missing_docs)]
-#![doc = include_str!("../README.md")]
+//! The command line interface of the Locking Pomodoro Timer.
+//!
+//! The timer itself is implemented in the `locking_pomodoro_timer` library.
 use std::path::PathBuf;
 
-use crate::pomodoro_options::{
-    read_options_from_json, write_default_options_to_json_next_to_executable,
+use anyhow::Result;
+use chrono::Local;
+use clap::{Args, Parser, Subcommand};
+#[cfg(unix)]
+use locking_pomodoro_timer::control::{get_filepath_socket, send_command, ControlCommand};
+use locking_pomodoro_timer::history::{get_filepath_history, read_records};
+use locking_pomodoro_timer::pomodoro_options::{
+    convert_options_file, read_profile_from_file, write_default_options_to_json_next_to_executable,
 };
-use crate::pomodoro_options::{PomodoroOptions, PomodoroOptionsError};
-use crate::cli_utilities::start_pomodoro;
-mod input_handler;
-mod message_creator;
-mod pomo_info;
-mod pomodoro_options;
-mod timer;
-mod cli_utilities;
-mod end_events;
+use locking_pomodoro_timer::pomodoro_options::{PomodoroOptions, PomodoroOptionsError};
+use locking_pomodoro_timer::simulation::{parse_speed, simulate};
+use locking_pomodoro_timer::stats::{
+    create_stats, format_stats, parse_since, GroupBy, StatsFormat,
+};
+use locking_pomodoro_timer::{start_pomodoro, SessionOutcome};
+
+/// A Pomodoro timer with the capability of locking the screen.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The command to run. Without a command, the Pomodoro timer is started.
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// The path to the options file, in JSON, TOML or YAML. By default, `pomodoro_options.json`,
+    /// `.toml` or `.yaml` next to the executable is used.
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// The profile of the options file to use instead of the default profile.
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+    /// The label of the task to start with.
+    #[arg(long)]
+    label: Option<String>,
+    /// Options overriding the options file for this session only.
+    #[command(flatten)]
+    overrides: OptionOverrides,
+}
+
+/// The command line arguments which override single fields of the `PomodoroOptions`.
+#[derive(Args)]
+struct OptionOverrides {
+    /// The duration of a Pomodoro in minutes.
+    #[arg(long, value_name = "MINUTES")]
+    duration: Option<i32>,
+    /// The additional duration after a Pomodoro in minutes.
+    #[arg(long, value_name = "MINUTES")]
+    additional_duration: Option<i32>,
+    /// The duration of a short break in minutes.
+    #[arg(long, value_name = "MINUTES")]
+    short_break: Option<i32>,
+    /// The duration of a long break in minutes.
+    #[arg(long, value_name = "MINUTES")]
+    long_break: Option<i32>,
+    /// The number of Pomodoros after which a long break is taken.
+    #[arg(long, value_name = "POMODOROS")]
+    long_break_interval: Option<i32>,
+    /// Start the breaks automatically.
+    #[arg(long, conflicts_with = "no_auto_start_break")]
+    auto_start_break: bool,
+    /// Ask before starting a break.
+    #[arg(long)]
+    no_auto_start_break: bool,
+    /// Start the next Pomodoro automatically after a break.
+    #[arg(long, conflicts_with = "no_auto_start_pomodoro")]
+    auto_start_pomodoro: bool,
+    /// Ask before starting the next Pomodoro.
+    #[arg(long)]
+    no_auto_start_pomodoro: bool,
+    /// The interval of the reminder after a break in minutes.
+    #[arg(long, value_name = "MINUTES")]
+    reminder_interval: Option<i32>,
+    /// The port of the local HTTP API.
+    #[arg(long, value_name = "PORT")]
+    http_port: Option<u16>,
+    /// Show the timer as a full-screen interface.
+    #[arg(long)]
+    tui: bool,
+}
+
+impl OptionOverrides {
+    /// Overrides the fields of the options which were given on the command line.
+    fn apply(&self, options: &mut PomodoroOptions) {
+        if let Some(duration) = self.duration {
+            options.duration_pomodoro = duration;
+        }
+        if let Some(additional_duration) = self.additional_duration {
+            options.additional_duration = additional_duration;
+        }
+        if let Some(short_break) = self.short_break {
+            options.duration_short_break = short_break;
+        }
+        if let Some(long_break) = self.long_break {
+            options.duration_long_break = long_break;
+        }
+        if let Some(long_break_interval) = self.long_break_interval {
+            options.interval_long_break = long_break_interval;
+        }
+        if self.auto_start_break {
+            options.auto_start_break = true;
+        }
+        if self.no_auto_start_break {
+            options.auto_start_break = false;
+        }
+        if self.auto_start_pomodoro {
+            options.auto_start_pomodoro = true;
+        }
+        if self.no_auto_start_pomodoro {
+            options.auto_start_pomodoro = false;
+        }
+        if let Some(reminder_interval) = self.reminder_interval {
+            options.interval_reminder_after_break = reminder_interval;
+        }
+        if let Some(http_port) = self.http_port {
+            options.http_port = Some(http_port);
+        }
+        if self.tui {
+            options.tui = true;
+        }
+    }
+}
+
+/// The subcommands of the command line interface.
+#[derive(Subcommand)]
+enum Command {
+    /// Print statistics of the recorded Pomodoros and breaks.
+    Stats(StatsArgs),
+    /// Simulate a cycle with the options without waiting. End events are listed instead of started.
+    Simulate(SimulateArgs),
+    /// Control the running timer, e.g. from a keybinding of the window manager.
+    #[cfg(unix)]
+    Ctl(CtlArgs),
+    /// Manage options files.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+/// The subcommands of the `config` subcommand.
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Convert an options file between JSON, TOML and YAML. The formats are detected from the
+    /// extensions of the files.
+    Convert(ConvertArgs),
+}
+
+/// The arguments of the `config convert` subcommand.
+#[derive(Args)]
+struct ConvertArgs {
+    /// The options file to convert.
+    input: PathBuf,
+    /// The converted options file, e.g. `pomodoro_options.toml`.
+    output: PathBuf,
+    /// Overwrite the converted options file if it already exists.
+    #[arg(long)]
+    force: bool,
+}
+
+/// The arguments of the `ctl` subcommand.
+#[cfg(unix)]
+#[derive(Args)]
+struct CtlArgs {
+    /// The command to send to the running timer.
+    #[command(subcommand)]
+    command: ControlCommand,
+    /// Print the answer of the timer as JSON.
+    #[arg(long, global = true)]
+    json: bool,
+}
+
+/// The arguments of the `stats` subcommand.
+#[derive(Args)]
+struct StatsArgs {
+    /// The start of the reported period, e.g. 12h, 7d, 2w, today or 2024-05-22.
+    #[arg(long, default_value = "7d")]
+    since: String,
+    /// How the phases are grouped.
+    #[arg(long, value_enum, default_value_t = GroupBy::Day)]
+    by: GroupBy,
+    /// The output format.
+    #[arg(long, value_enum, default_value_t = StatsFormat::Table)]
+    format: StatsFormat,
+}
+
+/// The arguments of the `simulate` subcommand.
+#[derive(Args)]
+struct SimulateArgs {
+    /// The speed of the simulation, e.g. 60x to let a minute pass every second, or virtual to not
+    /// wait at all.
+    #[arg(long, default_value = "virtual")]
+    speed: String,
+    /// The number of Pomodoros to simulate. By default, the Pomodoros until the long break.
+    #[arg(long, value_name = "POMODOROS")]
+    pomodoros: Option<i32>,
+}
+
 /// The main entry point of the program.
 ///
-/// This function initializes the logger, reads the Pomodoro options from a JSON file,
-/// and starts the Pomodoro timer. If the options file is not found, it writes default
-/// options to a new JSON file and informs the user.
+/// This function initializes the logger, parses the command line arguments, reads the Pomodoro
+/// options from the options file, applies the overrides of the command line and starts the Pomodoro
+/// timer or runs the given subcommand.
+/// If the options file next to the executable is not found, it writes default options to a new
+/// JSON file and informs the user. If the options are invalid, the program exits with an error.
 ///
 /// # Panics
 /// This function will panic if it fails to write default options to the JSON file.
 fn main() {
+    let cli = Cli::parse();
     // Initialize the logger
     let logging_config_file = PathBuf::from("pomodoro_logging.yaml");
     if logging_config_file.is_file() {
         log4rs::init_file(logging_config_file, Default::default()).unwrap();
     }
-    // Read the JSON file
-    let data = read_options_from_json(None);
-    let json_data = match data {
+
+    match &cli.command {
+        // Start the Pomodoro timer
+        None => {
+            let outcome = start_pomodoro(load_options(&cli), cli.label.clone());
+            exit_after_session(outcome);
+        }
+        Some(Command::Stats(args)) => {
+            if let Err(e) = print_stats(args, &load_options(&cli)) {
+                exit_with_error(e);
+            }
+        }
+        Some(Command::Simulate(args)) => {
+            if let Err(e) = run_simulation(args, load_options(&cli)) {
+                exit_with_error(e);
+            }
+        }
+        // The running timer has already read its options, so they are not needed here.
+        #[cfg(unix)]
+        Some(Command::Ctl(args)) => {
+            if let Err(e) = control_timer(args) {
+                exit_with_error(e);
+            }
+        }
+        // The options file is converted as a whole, including all profiles.
+        Some(Command::Config(ConfigCommand::Convert(args))) => {
+            if let Err(e) = convert_options_file(&args.input, &args.output, args.force) {
+                exit_with_error(e);
+            }
+            println!("Converted {:?} to {:?}.", args.input, args.output);
+        }
+    }
+}
+
+/// Loads the Pomodoro options selected on the command line and applies the overrides.
+///
+/// The program exits with an error if the options could not be read or are invalid.
+fn load_options(cli: &Cli) -> PomodoroOptions {
+    let mut options = match &cli.config {
+        Some(filepath_options) => {
+            read_profile_from_file(Some(filepath_options.clone()), cli.profile.as_deref())
+        }
+        None => read_options(cli.profile.as_deref()),
+    }
+    .unwrap_or_else(|e| exit_with_error(e));
+    cli.overrides.apply(&mut options);
+    if let Err(e) = options.verify() {
+        exit_with_error(e.into());
+    }
+    options
+}
+
+/// Reads the Pomodoro options of the profile from the options file next to the executable.
+///
+/// If the options file is not found, default options are written to a new JSON file.
+/// If the options file is invalid, the error is printed and default options are used.
+///
+/// # Errors
+///
+/// Returns an error if the profile is not defined in the options file.
+fn read_options(profile: Option<&str>) -> Result<PomodoroOptions> {
+    // Read the options file
+    let data = read_profile_from_file(None, profile);
+    let options = match data {
         Ok(json_data) => json_data,
         Err(e) => {
             // Handle the error when the option file is not found
@@ -49,8 +297,12 @@ fn main() {
                         https://github.com/davidzanger/Locking-Pomodoro-Timer.git"
                     );
 
+                    if let Some(name) = profile {
+                        return Err(PomodoroOptionsError::ProfileNotFound(name.to_string()).into());
+                    }
                     PomodoroOptions::default()
                 }
+                Some(_) => return Err(e),
                 None => {
                     // Print the error and use default options
                     eprintln!("Error: {:#}", e);
@@ -60,10 +312,89 @@ fn main() {
             }
         }
     };
+    Ok(options)
+}
+
+/// Exits the program after the session has ended and its view has restored the terminal.
+///
+/// The raw mode is disabled here, because the input thread only disables it when ctrl+c is
+/// pressed, but the program can also be quit by other keys, see `KeyBindings`. The program is
+/// exited explicitly, because the input thread and the servers are still running.
+fn exit_after_session(outcome: SessionOutcome) -> ! {
+    let _ = crossterm::terminal::disable_raw_mode();
+    if outcome == SessionOutcome::Quit {
+        println!("Exiting the program.");
+    }
+    std::process::exit(0);
+}
+
+/// Prints the error and exits the program with exit code 1.
+fn exit_with_error(e: anyhow::Error) -> ! {
+    eprintln!("Error: {:#}", e);
+    std::process::exit(1);
+}
+
+/// Prints the statistics of the history.
+///
+/// # Errors
+///
+/// Returns an error if the period is invalid or the history could not be read.
+fn print_stats(args: &StatsArgs, options: &PomodoroOptions) -> Result<()> {
+    let since = parse_since(&args.since, Local::now())?;
+    let records = read_records(&get_filepath_history(&options.filepath_history)?)?;
+    let stats = create_stats(&records, since, args.by);
+    println!("{}", format_stats(&stats, args.format)?);
+    Ok(())
+}
+
+/// Simulates a cycle with the options and prints the timeline of the phases and end events.
+///
+/// # Errors
+///
+/// Returns an error if the speed is invalid.
+fn run_simulation(args: &SimulateArgs, options: PomodoroOptions) -> Result<()> {
+    let speed = parse_speed(&args.speed)?;
+    let pomodoros = args.pomodoros.unwrap_or(options.interval_long_break);
+    let report = simulate(options, speed, pomodoros);
+    println!("{}", report);
+    Ok(())
+}
+
+/// Sends the command to the running timer and prints its answer.
+///
+/// # Errors
+///
+/// Returns an error if no timer is running or if the timer rejected the command.
+#[cfg(unix)]
+fn control_timer(args: &CtlArgs) -> Result<()> {
+    let response = send_command(&get_filepath_socket(), args.command)?;
+    if args.json {
+        println!("{}", serde_json::to_string(&response)?);
+    }
+    if !response.ok {
+        anyhow::bail!(response.error.unwrap_or_default());
+    }
+    if let (Some(status), false) = (response.status, args.json) {
+        match status.phase {
+            Some(phase) => println!(
+                "{} | elapsed {} | remaining {}{}{}",
+                phase,
+                format_seconds(status.elapsed_seconds),
+                format_seconds(status.remaining_seconds),
+                if status.paused { " | paused" } else { "" },
+                status
+                    .label
+                    .map(|label| format!(" | Task: {}", label))
+                    .unwrap_or_default()
+            ),
+            None => println!("No timer is running."),
+        }
+    }
+    Ok(())
+}
 
-    // Start the Pomodoro timer
-    start_pomodoro(&json_data)
-    // if let Err(e) = std::panic::catch_unwind(|| start_pomodoro(&json_data)) {
-        // log::error!("An error occurred: {:#?}", e);
-    // }
+/// Formats the seconds as minutes and seconds, e.g. "12:05".
+#[cfg(unix)]
+fn format_seconds(seconds: u64) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
 }
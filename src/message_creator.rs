@@ -1,4 +1,4 @@
-/// This module contains functions and structs related to creating terminal print messages for the Pomodoro app.
+//! This module contains functions and structs related to creating terminal print messages for the Pomodoro app.
 use crate::pomodoro_options::PomodoroOptions;
 
 use crate::pomo_info::PomoInfo;
@@ -12,16 +12,18 @@ struct MessageData<'a> {
     upcoming_duration: i32,
     pomodoros_till_long_break: i32,
     minutes_till_long_break: i32,
+    label: Option<&'a str>,
 }
 
 impl MessageData<'_> {
     /// Generates a formatted print message with the current, upcoming, and pomodoro till long break information.
+    /// If a task label is set, it is appended to the message.
     ///
     /// # Returns
     ///
     /// A string containing the formatted print message.
     fn generate_print_message(&self) -> String {
-        format!(
+        let message = format!(
             "Current: {} ({:.0} min) | Upcoming: {} ({:.0} min) | Pomodoros till long break: {} ({:.0} min)",
             self.current,
             self.current_duration,
@@ -29,7 +31,11 @@ impl MessageData<'_> {
             self.upcoming_duration,
             self.pomodoros_till_long_break,
             self.minutes_till_long_break
-        )
+        );
+        match self.label {
+            Some(label) => format!("{} | Task: {}", message, label),
+            None => message,
+        }
     }
 }
 
@@ -39,6 +45,7 @@ impl MessageData<'_> {
 ///
 /// * `pomo_info` - The Pomodoro information.
 /// * `options` - The Pomodoro options.
+/// * `label` - The label of the current task, if any.
 ///
 /// # Returns
 ///
@@ -50,6 +57,7 @@ impl MessageData<'_> {
 pub(crate) fn generate_print_message_before_pomodoro(
     pomo_info: &PomoInfo,
     options: &PomodoroOptions,
+    label: Option<&str>,
 ) -> String {
     let minutes_till_long_break = pomo_info.pomodoros_till_long_break
         * (options.duration_pomodoro + options.additional_duration)
@@ -79,9 +87,9 @@ pub(crate) fn generate_print_message_before_pomodoro(
         upcoming_duration,
         pomodoros_till_long_break: pomo_info.pomodoros_till_long_break,
         minutes_till_long_break,
+        label,
     };
-    let print_message = message_data.generate_print_message();
-    print_message
+    message_data.generate_print_message()
 }
 
 /// Generates a print message to be displayed before starting an additional break.
@@ -90,6 +98,7 @@ pub(crate) fn generate_print_message_before_pomodoro(
 ///
 /// * `pomo_info` - The Pomodoro information.
 /// * `options` - The Pomodoro options.
+/// * `label` - The label of the current task, if any.
 ///
 /// # Returns
 ///
@@ -101,6 +110,7 @@ pub(crate) fn generate_print_message_before_pomodoro(
 pub(crate) fn generate_print_message_before_additional_break(
     pomo_info: &PomoInfo,
     options: &PomodoroOptions,
+    label: Option<&str>,
 ) -> String {
     let minutes_till_long_break = pomo_info.pomodoros_till_long_break * options.additional_duration
         + (pomo_info.pomodoros_till_long_break - 1)
@@ -127,9 +137,9 @@ pub(crate) fn generate_print_message_before_additional_break(
         upcoming_duration,
         pomodoros_till_long_break: pomo_info.pomodoros_till_long_break,
         minutes_till_long_break,
+        label,
     };
-    let print_message = message_data.generate_print_message();
-    print_message
+    message_data.generate_print_message()
 }
 
 /// Generates a print message to be displayed before starting a break.
@@ -138,6 +148,7 @@ pub(crate) fn generate_print_message_before_additional_break(
 ///
 /// * `pomo_info` - The Pomodoro information.
 /// * `options` - The Pomodoro options.
+/// * `label` - The label of the current task, if any.
 ///
 /// # Returns
 ///
@@ -149,6 +160,7 @@ pub(crate) fn generate_print_message_before_additional_break(
 pub(crate) fn generate_print_message_before_break(
     pomo_info: &PomoInfo,
     options: &PomodoroOptions,
+    label: Option<&str>,
 ) -> String {
     let (current, current_duration, upcoming, upcoming_duration); 
     current = if pomo_info.is_long_break_coming {
@@ -168,7 +180,19 @@ pub(crate) fn generate_print_message_before_break(
         upcoming_duration,
         pomodoros_till_long_break: pomo_info.pomodoros_till_long_break - 1,
         minutes_till_long_break,
+        label,
     };
-    let print_message = message_data.generate_print_message();
-    print_message
+    message_data.generate_print_message()
+}
+
+#[test]
+fn test_generate_print_message_with_label() {
+    let options = PomodoroOptions::default();
+    let pomo_info = PomoInfo::from_options(&options, 0);
+    let message = generate_print_message_before_pomodoro(&pomo_info, &options, None);
+    assert!(!message.contains("Task"));
+    let message = generate_print_message_before_pomodoro(&pomo_info, &options, Some("coding"));
+    assert!(message
+        .starts_with("Current: Pomodoro (25 min) | Upcoming: Additional Pomodoro (5 min)"));
+    assert!(message.ends_with(" | Task: coding"));
 }
@@ -0,0 +1,200 @@
+//! This module contains the desktop notifications shown by the `notification` end event.
+//!
+//! On Linux, notifications are sent to the notification server of the desktop through the
+//! freedesktop `org.freedesktop.Notifications` D-Bus interface. If no notification server is
+//! reachable over D-Bus, e.g. because the timer runs in a container, `notify-send` is used instead.
+use anyhow::{bail, Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+/// The application name shown by the notification server.
+const APP_NAME: &str = "Locking Pomodoro Timer";
+
+/// Represents the urgency level of a notification.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Urgency {
+    /// The notification is not important, e.g. the end of a break.
+    Low,
+    /// The default urgency.
+    #[default]
+    Normal,
+    /// The notification stays visible until it is closed by the user on most desktops.
+    Critical,
+}
+
+impl Urgency {
+    /// Gets the urgency level defined by the freedesktop notification specification.
+    fn level(&self) -> u8 {
+        match self {
+            Urgency::Low => 0,
+            Urgency::Normal => 1,
+            Urgency::Critical => 2,
+        }
+    }
+
+    /// Gets the name used by `notify-send`.
+    fn name(&self) -> &'static str {
+        match self {
+            Urgency::Low => "low",
+            Urgency::Normal => "normal",
+            Urgency::Critical => "critical",
+        }
+    }
+}
+
+/// Shows a desktop notification.
+///
+/// The notification is sent over D-Bus. If that fails, `notify-send` is tried.
+///
+/// # Arguments
+///
+/// * `summary` - The title of the notification.
+/// * `body` - The text of the notification.
+/// * `urgency` - The urgency level of the notification.
+/// * `timeout` - The time in milliseconds after which the notification is closed. If `None`,
+///   the default of the notification server is used.
+///
+/// # Errors
+///
+/// Returns an error containing the reason of both attempts if the notification could not be shown.
+pub fn show_notification(
+    summary: &str,
+    body: &str,
+    urgency: Urgency,
+    timeout: Option<u32>,
+) -> Result<()> {
+    let dbus_error = match notify_over_dbus(summary, body, urgency, timeout) {
+        Ok(()) => return Ok(()),
+        Err(e) => e,
+    };
+    debug!(
+        "Failed to send the notification over D-Bus, trying notify-send: {:#}",
+        dbus_error
+    );
+    notify_with_notify_send(summary, body, urgency, timeout).with_context(|| {
+        format!(
+            "Failed to show the notification over D-Bus ({:#}) and with notify-send",
+            dbus_error
+        )
+    })
+}
+
+/// Sends the notification with `Notify` on the `org.freedesktop.Notifications` D-Bus interface.
+#[cfg(target_os = "linux")]
+fn notify_over_dbus(
+    summary: &str,
+    body: &str,
+    urgency: Urgency,
+    timeout: Option<u32>,
+) -> Result<()> {
+    use std::collections::HashMap;
+    use zbus::zvariant::Value;
+
+    let connection =
+        zbus::blocking::Connection::session().context("Failed to connect to the session bus.")?;
+    let hints = HashMap::from([("urgency", Value::U8(urgency.level()))]);
+    // -1 lets the notification server decide.
+    let timeout = timeout.map_or(-1, |timeout| timeout.min(i32::MAX as u32) as i32);
+    connection
+        .call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "Notify",
+            &(
+                APP_NAME,
+                0u32,
+                "",
+                summary,
+                body,
+                Vec::<&str>::new(),
+                hints,
+                timeout,
+            ),
+        )
+        .context("Failed to call `Notify` on org.freedesktop.Notifications.")?;
+    Ok(())
+}
+
+/// The freedesktop notification interface is only available on Linux.
+#[cfg(not(target_os = "linux"))]
+fn notify_over_dbus(
+    _summary: &str,
+    _body: &str,
+    _urgency: Urgency,
+    _timeout: Option<u32>,
+) -> Result<()> {
+    bail!("D-Bus notifications are only available on Linux.")
+}
+
+/// Shows the notification using `notify-send`.
+fn notify_with_notify_send(
+    summary: &str,
+    body: &str,
+    urgency: Urgency,
+    timeout: Option<u32>,
+) -> Result<()> {
+    let output = Command::new("notify-send")
+        .args(notify_send_args(summary, body, urgency, timeout))
+        .output()
+        .context("Failed to run `notify-send`.")?;
+    if !output.status.success() {
+        bail!(
+            "`notify-send` exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Gets the command line arguments of `notify-send` for the notification.
+fn notify_send_args(
+    summary: &str,
+    body: &str,
+    urgency: Urgency,
+    timeout: Option<u32>,
+) -> Vec<String> {
+    let mut args = vec![
+        format!("--app-name={}", APP_NAME),
+        format!("--urgency={}", urgency.name()),
+    ];
+    if let Some(timeout) = timeout {
+        args.push(format!("--expire-time={}", timeout));
+    }
+    // Everything after `--` is taken literally, even if the summary starts with a dash.
+    args.extend(["--".to_string(), summary.to_string(), body.to_string()]);
+    args
+}
+
+#[test]
+fn test_notify_send_args() {
+    assert_eq!(
+        notify_send_args(
+            "Pomodoro finished",
+            "Take a break.",
+            Urgency::Critical,
+            Some(5000)
+        ),
+        vec![
+            "--app-name=Locking Pomodoro Timer",
+            "--urgency=critical",
+            "--expire-time=5000",
+            "--",
+            "Pomodoro finished",
+            "Take a break.",
+        ]
+    );
+    assert_eq!(
+        notify_send_args("-5 minutes", "", Urgency::default(), None),
+        vec![
+            "--app-name=Locking Pomodoro Timer",
+            "--urgency=normal",
+            "--",
+            "-5 minutes",
+            "",
+        ]
+    );
+}
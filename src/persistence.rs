@@ -0,0 +1,279 @@
+//! This module persists the state of the running session, so a session which was interrupted,
+//! e.g. because the terminal was closed or the machine rebooted, can be resumed on the next start.
+//!
+//! While a session is running, the running phase, its elapsed time, the pause state, the Pomodoro
+//! counter and the task label are written to a small JSON file every few seconds. The file is
+//! removed when a phase has ended and when the session ends regularly, so an existing file means
+//! the last session was interrupted while a phase was running.
+use crate::pomo_info::PomoInfo;
+use crate::pomodoro_options::{get_folderpath_executable, PomodoroOptions};
+use crate::session::{Phase, SessionStatus, StatusHandle};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// The interval in which the state of the running session is saved.
+const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Represents the saved state of a running session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedState {
+    /// The phase which was running.
+    pub phase: Phase,
+    /// The elapsed time of the phase in seconds, including skipped time.
+    pub elapsed_seconds: u64,
+    /// The remaining time of the phase in seconds.
+    pub remaining_seconds: u64,
+    /// Indicates whether the timer was paused.
+    pub paused: bool,
+    /// The number of completed Pomodoros.
+    pub counter: i32,
+    /// The label of the task worked on, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The wall-clock time when the state was saved.
+    pub saved_at: DateTime<Local>,
+}
+
+impl SavedState {
+    /// Creates the state to save from the status of a session.
+    ///
+    /// Returns `None` if no phase is running.
+    pub fn from_status(status: &SessionStatus) -> Option<Self> {
+        Some(SavedState {
+            phase: status.phase?,
+            elapsed_seconds: status.elapsed_seconds,
+            remaining_seconds: status.remaining_seconds,
+            paused: status.paused,
+            counter: status.counter,
+            label: status.label.clone(),
+            saved_at: Local::now(),
+        })
+    }
+
+    /// Describes the interrupted phase, e.g. "Pomodoro (12 min left, 3 till long break)".
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The Pomodoro options, used to calculate the Pomodoros till the long break.
+    pub fn describe(&self, options: &PomodoroOptions) -> String {
+        let pomo_info = PomoInfo::from_options(options, self.counter);
+        // The Pomodoro before a break is already finished.
+        let pomodoros_till_long_break = match self.phase {
+            Phase::Pomodoro | Phase::AdditionalPomodoro => pomo_info.pomodoros_till_long_break,
+            Phase::ShortBreak | Phase::LongBreak => pomo_info.pomodoros_till_long_break - 1,
+        };
+        format!(
+            "{} ({} min left, {} till long break{})",
+            self.phase,
+            self.remaining_seconds.div_ceil(60),
+            pomodoros_till_long_break,
+            if self.paused { ", paused" } else { "" }
+        )
+    }
+}
+
+/// Gets the path to the state file.
+///
+/// If `filepath_state` is empty, the state file is located next to the executable.
+///
+/// # Errors
+///
+/// Returns an error if the folder of the executable could not be determined.
+pub fn get_filepath_state(filepath_state: &Path) -> Result<PathBuf> {
+    if !filepath_state.as_os_str().is_empty() {
+        return Ok(filepath_state.to_path_buf());
+    }
+    let mut path = get_folderpath_executable()?;
+    path.push("pomodoro_state.json");
+    Ok(path)
+}
+
+/// Saves the state to the state file.
+///
+/// The state is written to a temporary file first, which then replaces the state file, so the
+/// state file is never left half written.
+///
+/// # Errors
+///
+/// Returns an error if the state file could not be written.
+pub fn save_state(filepath_state: &Path, state: &SavedState) -> Result<()> {
+    if let Some(folder) = filepath_state.parent() {
+        std::fs::create_dir_all(folder)
+            .with_context(|| format!("Failed to create folder: {:?}", folder))?;
+    }
+    let filepath_temporary = filepath_state.with_extension("json.tmp");
+    let contents = serde_json::to_string(state).context("Failed to serialize state.")?;
+    std::fs::write(&filepath_temporary, contents)
+        .with_context(|| format!("Failed to write state file: {:?}", filepath_temporary))?;
+    std::fs::rename(&filepath_temporary, filepath_state)
+        .with_context(|| format!("Failed to replace state file: {:?}", filepath_state))?;
+    Ok(())
+}
+
+/// Reads the state of an interrupted session.
+///
+/// # Returns
+///
+/// The saved state or `None` if the state file does not exist.
+///
+/// # Errors
+///
+/// Returns an error if the state file exists but could not be read or parsed.
+pub fn read_state(filepath_state: &Path) -> Result<Option<SavedState>> {
+    if !filepath_state.is_file() {
+        return Ok(None);
+    }
+    let contents = std::fs::read_to_string(filepath_state)
+        .with_context(|| format!("Failed to read state file: {:?}", filepath_state))?;
+    let state = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse state file: {:?}", filepath_state))?;
+    Ok(Some(state))
+}
+
+/// Removes the state file, e.g. when the session has ended regularly.
+///
+/// # Errors
+///
+/// Returns an error if the state file exists but could not be removed.
+pub fn remove_state(filepath_state: &Path) -> Result<()> {
+    match std::fs::remove_file(filepath_state) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove state file: {:?}", filepath_state))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Represents the requests to the thread of a `StateSaver`.
+enum SaverRequest {
+    /// Save the state now.
+    Update,
+    /// Stop saving the state.
+    Stop,
+}
+
+/// Saves the state of a running session periodically in a separate thread.
+pub struct StateSaver {
+    requests: Sender<SaverRequest>,
+    thread: JoinHandle<()>,
+}
+
+impl StateSaver {
+    /// Starts saving the status of the session every few seconds.
+    ///
+    /// While no phase is running, e.g. while waiting for the user, the state file is removed, so
+    /// a finished phase is not offered to be resumed. Errors are logged, so that a broken state
+    /// file does not stop the timer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the thread could not be spawned.
+    pub fn start(status: StatusHandle, filepath_state: PathBuf) -> Result<Self> {
+        let (requests, request_receiver) = channel();
+        let thread = thread::Builder::new()
+            .name("state_saver".to_string())
+            .spawn(move || {
+                while let Ok(SaverRequest::Update) | Err(RecvTimeoutError::Timeout) =
+                    request_receiver.recv_timeout(SAVE_INTERVAL)
+                {
+                    let result = match SavedState::from_status(&status.status()) {
+                        Some(state) => {
+                            debug!("Saving state: {:?}", state);
+                            save_state(&filepath_state, &state)
+                        }
+                        None => remove_state(&filepath_state),
+                    };
+                    if let Err(e) = result {
+                        error!("Failed to save the state of the session: {:#}", e);
+                    }
+                }
+            })
+            .context("Failed to spawn state saver thread.")?;
+        Ok(StateSaver { requests, thread })
+    }
+
+    /// Saves the state now instead of waiting for the next interval, e.g. after a phase has
+    /// ended. If no phase is running anymore, the state file is removed.
+    ///
+    /// The requests are handled in order by the thread of the saver, so a state which is saved
+    /// at the same time can not restore the file afterwards.
+    pub fn update(&self) {
+        // The thread only stops after `stop`, so a failed send is no problem.
+        let _ = self.requests.send(SaverRequest::Update);
+    }
+
+    /// Stops saving the state and waits for the thread to finish.
+    pub fn stop(self) {
+        // The thread also stops if the sender is dropped, so a failed send is no problem.
+        let _ = self.requests.send(SaverRequest::Stop);
+        let _ = self.thread.join();
+    }
+}
+
+#[test]
+fn test_save_and_read_state() {
+    let filepath_state = std::env::temp_dir()
+        .join(format!("pomodoro_state_test_{}", std::process::id()))
+        .join("state.json");
+    assert_eq!(read_state(&filepath_state).unwrap(), None);
+    let status = SessionStatus {
+        phase: Some(Phase::Pomodoro),
+        elapsed_seconds: 780,
+        remaining_seconds: 720,
+        paused: true,
+        counter: 1,
+        label: Some("Report".to_string()),
+    };
+    let state = SavedState::from_status(&status).unwrap();
+
+    save_state(&filepath_state, &state).unwrap();
+    let read = read_state(&filepath_state).unwrap();
+    remove_state(&filepath_state).unwrap();
+    remove_state(&filepath_state).unwrap();
+    let exists = filepath_state.exists();
+    std::fs::remove_dir_all(filepath_state.parent().unwrap()).unwrap();
+
+    assert_eq!(read, Some(state.clone()));
+    assert!(!exists);
+    assert_eq!(
+        state.describe(&PomodoroOptions::default()),
+        "Pomodoro (12 min left, 3 till long break, paused)"
+    );
+    let status = SessionStatus {
+        phase: None,
+        ..status
+    };
+    assert_eq!(SavedState::from_status(&status), None);
+}
+
+#[test]
+fn test_state_saver_removes_finished_phase() {
+    let filepath_state = std::env::temp_dir()
+        .join(format!("pomodoro_state_saver_test_{}", std::process::id()))
+        .join("state.json");
+    let status = SessionStatus {
+        phase: Some(Phase::Pomodoro),
+        elapsed_seconds: 1500,
+        remaining_seconds: 0,
+        paused: false,
+        counter: 0,
+        label: None,
+    };
+    save_state(&filepath_state, &SavedState::from_status(&status).unwrap()).unwrap();
+
+    // No phase is running, e.g. while the user is asked to start the break.
+    let state_saver = StateSaver::start(StatusHandle::default(), filepath_state.clone()).unwrap();
+    state_saver.update();
+    state_saver.stop();
+    let exists = filepath_state.exists();
+    std::fs::remove_dir_all(filepath_state.parent().unwrap()).unwrap();
+
+    assert!(!exists);
+}
@@ -1,15 +1,17 @@
+//! This module contains the `PomoInfo` which is derived from the options and the Pomodoro counter.
 use std::time::Duration;
 
 use crate::pomodoro_options::PomodoroOptions;
 
 /// Represents the information related to a Pomodoro session.
-pub(crate) struct PomoInfo {
+#[derive(Debug, Clone, PartialEq)]
+pub struct PomoInfo {
     /// The number of Pomodoros remaining until a long break is triggered.
-    pub(crate) pomodoros_till_long_break: i32,
+    pub pomodoros_till_long_break: i32,
     /// Indicates whether a long break is approaching.
-    pub(crate) is_long_break_coming: bool,
+    pub is_long_break_coming: bool,
     /// The duration of the next break.
-    pub(crate) break_duration: Duration,
+    pub break_duration: Duration,
 }
 
 impl PomoInfo {
@@ -23,16 +25,15 @@ impl PomoInfo {
     /// # Returns
     ///
     /// A new `PomoInfo` instance with the calculated values.
-    pub(crate) fn from_options(options: &PomodoroOptions, counter: i32) -> Self {
+    pub fn from_options(options: &PomodoroOptions, counter: i32) -> Self {
         let pomodoros_till_long_break =
             options.interval_long_break - counter % options.interval_long_break;
         let is_long_break_coming =
             counter % options.interval_long_break == options.interval_long_break - 1;
-        let break_duration: Duration;
-        if is_long_break_coming {
-            break_duration = Duration::from_secs((options.duration_long_break * 60) as u64)
+        let break_duration = if is_long_break_coming {
+            Duration::from_secs((options.duration_long_break * 60) as u64)
         } else {
-            break_duration = Duration::from_secs((options.duration_short_break * 60) as u64)
+            Duration::from_secs((options.duration_short_break * 60) as u64)
         };
         PomoInfo {
             pomodoros_till_long_break,
@@ -1,16 +1,25 @@
-use crate::end_events::EndEvent;
-use anyhow::{Context, Result};
+//! This module contains the `PomodoroOptions` and functions to read and write them from and to JSON files.
+#[cfg(test)]
+use crate::end_events::BuiltinSound;
+use crate::end_events::{EndEvent, TimedEvent};
+use crate::key_bindings::{KeyBindings, MAX_MINUTES};
+use crate::screen_locker::ScreenLocker;
+use crate::session::Phase;
+use crate::toml_document::{format_toml, parse_toml};
+use anyhow::{bail, Context, Result};
 #[cfg(test)]
 use project_root::get_project_root;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::env;
 use std::fs::File;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use thiserror::Error;
 
 /// Struct representing the options for a Pomodoro timer.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase", default = "PomodoroOptions::default")]
 pub struct PomodoroOptions {
     /// The duration of a single Pomodoro session in minutes.
@@ -27,31 +36,86 @@ pub struct PomodoroOptions {
     pub auto_start_pomodoro: bool,
     /// The interval in number of Pomodoro sessions after which a long break should be taken.
     pub interval_long_break: i32,
+    /// The event to be executed when a Pomodoro session starts.
+    pub start_event_pomodoro: EndEvent,
     /// The end event to be executed after a Pomodoro session ends.
     pub end_event_pomodoro: EndEvent,
     /// The end event to be executed after the additional Pomodoro after a Pomodoro session ends.
     pub end_event_additional_pomodoro: EndEvent,
+    /// The event to be executed when a short break starts.
+    pub start_event_short_break: EndEvent,
+    /// The end event to be executed after a short break ends.
+    /// If `None`, `end_event_pomodoro` is used like in earlier versions.
+    pub end_event_short_break: Option<EndEvent>,
+    /// The event to be executed when a long break starts.
+    pub start_event_long_break: EndEvent,
+    /// The end event to be executed after a long break ends.
+    /// If `None`, `end_event_pomodoro` is used like in earlier versions.
+    pub end_event_long_break: Option<EndEvent>,
+    /// The events to be executed while a phase is running, e.g. a soft chime one minute before
+    /// the Pomodoro ends or at the halfway mark.
+    pub timed_events: Vec<TimedEvent>,
     /// After a break ends, the interval in minutes after which a reminder should be triggered.
     /// This shall remind the user to either go back to work or to start a new Pomodoro session if already working.
     /// This option is only relevant if `auto_start_pomodoro` is `false`.
     pub interval_reminder_after_break: i32,
     /// The event to be executed after the reminder interval after a break ends.
     pub event_reminder_after_break: EndEvent,
+    /// The screen lockers to try in order when locking the screen on Linux.
+    /// If empty, the order is detected from the desktop session.
+    pub screen_lockers: Vec<ScreenLocker>,
+    /// The path to the JSON Lines file where every phase is recorded.
+    /// If empty, the file `pomodoro_history.jsonl` next to the executable is used.
+    pub filepath_history: PathBuf,
+    /// The path to the file where the state of the running session is saved, so an interrupted
+    /// session can be resumed. If empty, the file `pomodoro_state.json` next to the executable is used.
+    pub filepath_state: PathBuf,
+    /// Flag indicating whether to ask for the label of the task before every Pomodoro.
+    /// The label can also be changed during a phase by pressing 'l'.
+    pub ask_for_label: bool,
+    /// The port of the local HTTP API on 127.0.0.1. If `None`, the HTTP API is disabled.
+    pub http_port: Option<u16>,
+    /// Flag indicating whether to show the timer as a full-screen interface instead of a
+    /// progress bar and scrolling messages.
+    pub tui: bool,
+    /// The keys of the actions, e.g. space to toggle the pause.
+    pub key_bindings: KeyBindings,
 }
 
 /// Error type for verification errors of `PomodoroOptions`.
 #[derive(Error, Debug)]
-pub(crate) enum VerificationError {
+#[allow(clippy::enum_variant_names)]
+pub enum VerificationError {
+    /// The Pomodoro duration is less than 1 minute.
     #[error("Pomodoro duration should be at least 1 minute.")]
     InvalidDuration,
+    /// The additional duration is negative.
     #[error("Additional duration should be at least 0 minute.")]
     InvalidAdditionalDuration,
+    /// The short break duration is negative.
     #[error("Short break duration should be at least 0 minute.")]
     InvalidShortBreakDuration,
+    /// The long break duration is negative.
     #[error("Long break duration should be at least 0 minute.")]
     InvalidLongBreakDuration,
+    /// The long break interval is less than 1 Pomodoro.
+    #[error("Long break interval should be at least 1 Pomodoro.")]
+    InvalidLongBreakInterval,
+    /// The time of a timed event is not within the phase.
+    #[error("The fraction of a timed event should be greater than 0 and less than 1.")]
+    InvalidTimedEvent,
+    /// The sound file of an end event does not exist.
     #[error("Sound file does not exist.")]
     InvalidSoundFile,
+    /// A key is bound to more than one action.
+    #[error("A key should be bound to at most one action.")]
+    InvalidKeyBindings,
+    /// The keys skip or extend a phase by more than `MAX_MINUTES`.
+    #[error(
+        "The keys should skip or extend a phase by at most {} minutes.",
+        MAX_MINUTES
+    )]
+    InvalidKeyBindingMinutes,
 }
 
 impl Default for PomodoroOptions {
@@ -65,14 +129,29 @@ impl Default for PomodoroOptions {
             auto_start_break: true,
             auto_start_pomodoro: true,
             interval_long_break: 4,
+            start_event_pomodoro: EndEvent::None,
             end_event_pomodoro: EndEvent::Sound {
                 filepath_sound: PathBuf::new(),
+                builtin: None,
             },
             end_event_additional_pomodoro: EndEvent::LockScreen,
+            start_event_short_break: EndEvent::None,
+            end_event_short_break: None,
+            start_event_long_break: EndEvent::None,
+            end_event_long_break: None,
+            timed_events: Vec::new(),
             interval_reminder_after_break: 5,
             event_reminder_after_break: EndEvent::Sound {
                 filepath_sound: PathBuf::new(),
+                builtin: None,
             },
+            screen_lockers: Vec::new(),
+            filepath_history: PathBuf::new(),
+            filepath_state: PathBuf::new(),
+            ask_for_label: false,
+            http_port: None,
+            tui: false,
+            key_bindings: KeyBindings::default(),
         }
     }
 }
@@ -83,7 +162,7 @@ impl PomodoroOptions {
     /// # Errors
     ///
     /// Returns a `VerificationError` if any of the options are invalid.
-    fn verify(&self) -> Result<(), VerificationError> {
+    pub fn verify(&self) -> Result<(), VerificationError> {
         if self.duration_pomodoro < 1 {
             return Err(VerificationError::InvalidDuration);
         }
@@ -96,61 +175,305 @@ impl PomodoroOptions {
         if self.duration_long_break < 0 {
             return Err(VerificationError::InvalidLongBreakDuration);
         }
-        if let EndEvent::Sound { filepath_sound } = &self.end_event_pomodoro {
-            if !PathBuf::from(&filepath_sound).is_file() && !filepath_sound.as_os_str().is_empty() {
-                return Err(VerificationError::InvalidSoundFile);
-            }
+        if self.interval_long_break < 1 {
+            return Err(VerificationError::InvalidLongBreakInterval);
         }
-        if let EndEvent::Sound { filepath_sound } = &self.end_event_additional_pomodoro {
-            if !PathBuf::from(&filepath_sound).is_file() && !filepath_sound.as_os_str().is_empty() {
-                return Err(VerificationError::InvalidSoundFile);
-            }
+        if self
+            .timed_events
+            .iter()
+            .any(|timed_event| !timed_event.at.is_valid())
+        {
+            return Err(VerificationError::InvalidTimedEvent);
+        }
+        if !self.key_bindings.is_valid() {
+            return Err(VerificationError::InvalidKeyBindings);
+        }
+        if self.key_bindings.skip_minutes > MAX_MINUTES
+            || self.key_bindings.extend_minutes > MAX_MINUTES
+        {
+            return Err(VerificationError::InvalidKeyBindingMinutes);
+        }
+        let mut filepaths_sound = Vec::new();
+        for end_event in self.events() {
+            end_event.collect_sound_files(&mut filepaths_sound);
+        }
+        if filepaths_sound.iter().any(|filepath_sound| {
+            !filepath_sound.is_file() && !filepath_sound.as_os_str().is_empty()
+        }) {
+            return Err(VerificationError::InvalidSoundFile);
         }
 
         Ok(())
     }
+
+    /// Gets the event to be executed when the phase starts.
+    ///
+    /// The additional Pomodoro continues the Pomodoro, so it has no start event.
+    pub fn start_event(&self, phase: Phase) -> &EndEvent {
+        static NO_EVENT: EndEvent = EndEvent::None;
+        match phase {
+            Phase::Pomodoro => &self.start_event_pomodoro,
+            Phase::AdditionalPomodoro => &NO_EVENT,
+            Phase::ShortBreak => &self.start_event_short_break,
+            Phase::LongBreak => &self.start_event_long_break,
+        }
+    }
+
+    /// Gets the end event to be executed when the phase has finished.
+    ///
+    /// If no end event is set for a break, the end event of the Pomodoro is used.
+    pub fn end_event(&self, phase: Phase) -> &EndEvent {
+        match phase {
+            Phase::Pomodoro => &self.end_event_pomodoro,
+            Phase::AdditionalPomodoro => &self.end_event_additional_pomodoro,
+            Phase::ShortBreak => self
+                .end_event_short_break
+                .as_ref()
+                .unwrap_or(&self.end_event_pomodoro),
+            Phase::LongBreak => self
+                .end_event_long_break
+                .as_ref()
+                .unwrap_or(&self.end_event_pomodoro),
+        }
+    }
+
+    /// Gets the timed events of the phase with the elapsed time at which they are due.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - The running phase.
+    /// * `duration` - The duration of the phase.
+    pub fn timed_events_of(&self, phase: Phase, duration: Duration) -> Vec<(Duration, &EndEvent)> {
+        self.timed_events
+            .iter()
+            .filter(|timed_event| timed_event.phase == phase)
+            .filter_map(|timed_event| {
+                Some((timed_event.at.elapsed_time(duration)?, &timed_event.event))
+            })
+            .collect()
+    }
+
+    /// Gets all events of the options, including the reminder after a break.
+    fn events(&self) -> Vec<&EndEvent> {
+        let mut events = vec![
+            &self.start_event_pomodoro,
+            &self.end_event_pomodoro,
+            &self.end_event_additional_pomodoro,
+            &self.start_event_short_break,
+            &self.start_event_long_break,
+            &self.event_reminder_after_break,
+        ];
+        events.extend(self.end_event_short_break.as_ref());
+        events.extend(self.end_event_long_break.as_ref());
+        events.extend(
+            self.timed_events
+                .iter()
+                .map(|timed_event| &timed_event.event),
+        );
+        events
+    }
+
+    /// Gets all events of the options for modification, including the reminder after a break.
+    fn events_mut(&mut self) -> Vec<&mut EndEvent> {
+        let mut events = vec![
+            &mut self.start_event_pomodoro,
+            &mut self.end_event_pomodoro,
+            &mut self.end_event_additional_pomodoro,
+            &mut self.start_event_short_break,
+            &mut self.start_event_long_break,
+            &mut self.event_reminder_after_break,
+        ];
+        events.extend(self.end_event_short_break.as_mut());
+        events.extend(self.end_event_long_break.as_mut());
+        events.extend(
+            self.timed_events
+                .iter_mut()
+                .map(|timed_event| &mut timed_event.event),
+        );
+        events
+    }
 }
 
 /// Error type for `PomodoroOptions` related errors.
 #[derive(Error, Debug)]
-pub(crate) enum PomodoroOptionsError {
-    #[error("Failed to read options from JSON file at path: {:?}", _0)]
+pub enum PomodoroOptionsError {
+    /// The options file does not exist.
+    #[error("Failed to read options from file at path: {:?}", _0)]
     OptionFileNotFound(PathBuf),
+    /// The requested profile is not defined in the options file.
+    #[error("Profile {:?} is not defined in the options file.", _0)]
+    ProfileNotFound(String),
+    /// The extension of the options file does not belong to a supported format.
+    #[error(
+        "Unsupported format of the options file {:?}, expected .json, .toml, .yaml or .yml.",
+        _0
+    )]
+    UnsupportedFormat(PathBuf),
+    /// The output file of a conversion already exists.
+    #[error("The file {:?} already exists.", _0)]
+    FileExists(PathBuf),
 }
 
-/// Reads the `PomodoroOptions` from a JSON file.
+/// The file formats of the options file.
 ///
-/// If `filepath_json` is `Some`, it reads the options from the specified file.
-/// If `filepath_json` is `None`, it tries to find the options file next to the executable.
+/// All formats describe the same `PomodoroOptions`, including the profiles. TOML and YAML allow
+/// comments, e.g. to note why a setting was chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionsFormat {
+    /// JSON, the format of the options file written on the first run.
+    Json,
+    /// TOML.
+    Toml,
+    /// YAML.
+    Yaml,
+}
+
+impl OptionsFormat {
+    /// The formats in the order in which the options file next to the executable is looked for.
+    const ALL: [OptionsFormat; 3] = [
+        OptionsFormat::Json,
+        OptionsFormat::Toml,
+        OptionsFormat::Yaml,
+    ];
+
+    /// Detects the format of the options file from its extension.
+    ///
+    /// # Errors
+    ///
+    /// Returns `PomodoroOptionsError::UnsupportedFormat` if the extension is neither `json`,
+    /// `toml`, `yaml` nor `yml`.
+    pub fn from_path(file_path: &Path) -> Result<Self, PomodoroOptionsError> {
+        let extension = file_path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("json") => Ok(OptionsFormat::Json),
+            Some("toml") => Ok(OptionsFormat::Toml),
+            Some("yaml") | Some("yml") => Ok(OptionsFormat::Yaml),
+            _ => Err(PomodoroOptionsError::UnsupportedFormat(
+                file_path.to_path_buf(),
+            )),
+        }
+    }
+
+    /// The extensions of the options files in this format.
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            OptionsFormat::Json => &["json"],
+            OptionsFormat::Toml => &["toml"],
+            OptionsFormat::Yaml => &["yaml", "yml"],
+        }
+    }
+
+    /// Parses the contents of an options file in this format.
+    ///
+    /// The result is not checked against the `PomodoroOptions`, so it can still contain profiles.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the contents are not valid in this format.
+    pub fn parse(self, contents: &str) -> Result<Value> {
+        let value = match self {
+            OptionsFormat::Json => serde_json::from_str(contents)?,
+            OptionsFormat::Toml => parse_toml(contents)?,
+            OptionsFormat::Yaml => serde_yaml::from_str(contents)?,
+        };
+        Ok(value)
+    }
+
+    /// Formats the contents of an options file in this format.
+    ///
+    /// TOML has no null value, so fields set to `null` are left out. They are read as unset,
+    /// which is the default of every field that can be `null`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value can not be represented in this format, or if the formatted
+    /// contents would not be read as the same value.
+    pub fn format(self, value: &Value) -> Result<String> {
+        let contents = match self {
+            OptionsFormat::Json => serde_json::to_string_pretty(value)?,
+            OptionsFormat::Toml => format_toml(value)?,
+            OptionsFormat::Yaml => serde_yaml::to_string(value)?,
+        };
+        // Check the contents, so a broken options file is never written.
+        let mut expected = value.clone();
+        if self == OptionsFormat::Toml {
+            remove_null_fields(&mut expected);
+        }
+        let parsed = self
+            .parse(&contents)
+            .with_context(|| format!("The options written as {:?} can not be read.", self))?;
+        if parsed != expected {
+            bail!(
+                "The options written as {:?} are not read as the same options.",
+                self
+            );
+        }
+        Ok(contents)
+    }
+}
+
+/// Removes the fields set to `null` from all objects in the value.
+fn remove_null_fields(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            fields.retain(|_, field| !field.is_null());
+            fields.values_mut().for_each(remove_null_fields);
+        }
+        Value::Array(items) => items.iter_mut().for_each(remove_null_fields),
+        _ => (),
+    }
+}
+
+/// Reads the `PomodoroOptions` from an options file.
+///
+/// If `filepath_options` is `Some`, it reads the options from the specified file.
+/// If `filepath_options` is `None`, it tries to find the options file next to the executable.
+/// The format of the file is detected from its extension, see `OptionsFormat`.
+/// If the options file defines a `defaultProfile`, this profile is used.
 ///
 /// # Errors
 ///
 /// Returns a `PomodoroOptionsError` if the options file is not found or if there are any other errors during the process.
-pub fn read_options_from_json(filepath_json: Option<PathBuf>) -> Result<PomodoroOptions> {
-    let file_path = match filepath_json {
+pub fn read_options_from_file(filepath_options: Option<PathBuf>) -> Result<PomodoroOptions> {
+    read_profile_from_file(filepath_options, None)
+}
+
+/// Reads the `PomodoroOptions` of a named profile from an options file.
+///
+/// Besides the options themselves, which form the base section, the options file can contain
+/// named `profiles`. A profile only needs to specify the fields which differ from the base
+/// section, all other fields are inherited from it. If `profile` is `None`, the profile named by
+/// `defaultProfile` is used, or the base section if there is no default profile.
+///
+/// # Arguments
+///
+/// * `filepath_options` - The path to the options file. If `None`, the options file next to the executable is used.
+/// * `profile` - The name of the profile to read.
+///
+/// # Errors
+///
+/// Returns a `PomodoroOptionsError` if the options file or the profile is not found or if there are any other errors during the process.
+pub fn read_profile_from_file(
+    filepath_options: Option<PathBuf>,
+    profile: Option<&str>,
+) -> Result<PomodoroOptions> {
+    let file_path = match filepath_options {
         Some(path) => path,
         None => get_filepath_options_next_to_executable()?,
     };
-    if !file_path.is_file() {
-        return Err(PomodoroOptionsError::OptionFileNotFound(file_path).into());
-    }
-    let mut file =
-        File::open(&file_path).with_context(|| format!("Failed to open file: {:?}", file_path))?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)
-        .with_context(|| format!("Failed to read file: {:?}", file_path))?;
-
-    let mut data: PomodoroOptions = serde_json::from_str(&contents)
-        .with_context(|| format!("Failed to parse JSON file: {:?}", file_path))?;
+    let value = read_options_file(&file_path)?;
+    let value = select_profile(value, profile)?;
+    let mut data: PomodoroOptions = serde_json::from_value(value)
+        .with_context(|| format!("Failed to parse options file: {:?}", file_path))?;
     match data.verify() {
         Ok(_) => (),
         Err(VerificationError::InvalidSoundFile) => {
-            println!("Sound file does not exist. Using default sound.");
-            if let EndEvent::Sound { filepath_sound } = &mut data.end_event_pomodoro {
-                *filepath_sound = PathBuf::new();
-            }
-            if let EndEvent::Sound { filepath_sound } = &mut data.end_event_additional_pomodoro {
-                *filepath_sound = PathBuf::new();
+            println!("Sound file does not exist. Using built-in sound.");
+            for end_event in data.events_mut() {
+                end_event.clear_missing_sound_files();
             }
         }
         Err(e) => return Err(e.into()),
@@ -158,12 +481,108 @@ pub fn read_options_from_json(filepath_json: Option<PathBuf>) -> Result<Pomodoro
     Ok(data)
 }
 
+/// Reads the options file in the format of its extension, without selecting a profile.
+///
+/// # Errors
+///
+/// Returns a `PomodoroOptionsError` if the options file is not found or its format is not
+/// supported, or an error if the file can not be read or parsed.
+fn read_options_file(file_path: &Path) -> Result<Value> {
+    let format = OptionsFormat::from_path(file_path)?;
+    if !file_path.is_file() {
+        return Err(PomodoroOptionsError::OptionFileNotFound(file_path.to_path_buf()).into());
+    }
+    let mut file =
+        File::open(file_path).with_context(|| format!("Failed to open file: {:?}", file_path))?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .with_context(|| format!("Failed to read file: {:?}", file_path))?;
+    format
+        .parse(&contents)
+        .with_context(|| format!("Failed to parse options file: {:?}", file_path))
+}
+
+/// Converts an options file into the format of the output file, e.g. from JSON to TOML.
+///
+/// The base section and all profiles are converted. They are checked to be valid options before
+/// the output file is written. Comments are not carried over.
+///
+/// # Arguments
+///
+/// * `filepath_input` - The path to the options file to convert.
+/// * `filepath_output` - The path to the converted options file. Its extension selects the format.
+/// * `overwrite` - Whether an existing output file is overwritten.
+///
+/// # Errors
+///
+/// Returns a `PomodoroOptionsError` if the input file is not found, a format is not supported or
+/// the output file exists and is not overwritten, or an error if the options are invalid or a
+/// file can not be read or written.
+pub fn convert_options_file(
+    filepath_input: &Path,
+    filepath_output: &Path,
+    overwrite: bool,
+) -> Result<()> {
+    let format = OptionsFormat::from_path(filepath_output)?;
+    let value = read_options_file(filepath_input)?;
+    let mut profiles = vec![None];
+    if let Some(Value::Object(fields)) = value.get("profiles") {
+        profiles.extend(fields.keys().map(|name| Some(name.as_str())));
+    }
+    for profile in profiles {
+        let options = select_profile(value.clone(), profile)?;
+        serde_json::from_value::<PomodoroOptions>(options).with_context(|| match profile {
+            Some(name) => format!("Invalid options in profile {:?}", name),
+            None => "Invalid options in the base section".to_string(),
+        })?;
+    }
+    if !overwrite && filepath_output.exists() {
+        return Err(PomodoroOptionsError::FileExists(filepath_output.to_path_buf()).into());
+    }
+    let contents = format.format(&value)?;
+    std::fs::write(filepath_output, contents)
+        .with_context(|| format!("Failed to write to file: {:?}", filepath_output))?;
+    Ok(())
+}
+
+/// Merges the fields of the selected profile into the base section of the options file.
+///
+/// # Errors
+///
+/// Returns `PomodoroOptionsError::ProfileNotFound` if the selected profile is not defined.
+fn select_profile(mut options: Value, profile: Option<&str>) -> Result<Value> {
+    let Value::Object(fields) = &mut options else {
+        return match profile {
+            Some(name) => Err(PomodoroOptionsError::ProfileNotFound(name.to_string()).into()),
+            None => Ok(options),
+        };
+    };
+    let profiles = fields.remove("profiles");
+    let default_profile = fields.remove("defaultProfile");
+    let name = match profile {
+        Some(name) => name.to_string(),
+        None => match default_profile {
+            Some(Value::String(name)) => name,
+            _ => return Ok(options),
+        },
+    };
+    let profile_fields = profiles
+        .as_ref()
+        .and_then(|profiles| profiles.get(&name))
+        .and_then(Value::as_object)
+        .ok_or(PomodoroOptionsError::ProfileNotFound(name))?;
+    for (key, value) in profile_fields {
+        fields.insert(key.clone(), value.clone());
+    }
+    Ok(options)
+}
+
 /// Writes the default `PomodoroOptions` to a JSON file next to the executable.
 ///
 /// # Errors
 ///
 /// Returns an error if there are any errors during the process of writing the options to the file.
-pub(crate) fn write_default_options_to_json_next_to_executable() -> Result<()> {
+pub fn write_default_options_to_json_next_to_executable() -> Result<()> {
     let file_path = get_filepath_options_next_to_executable()?;
     let options = PomodoroOptions::default();
     write_options_to_json(&file_path, &options)
@@ -179,8 +598,8 @@ pub(crate) fn write_default_options_to_json_next_to_executable() -> Result<()> {
 /// # Errors
 ///
 /// Returns an error if there are any errors during the process of writing the options to the file.
-pub(crate) fn write_options_to_json(file_path: &PathBuf, options: &PomodoroOptions) -> Result<()> {
-    let file = File::create(&file_path)
+pub fn write_options_to_json(file_path: &PathBuf, options: &PomodoroOptions) -> Result<()> {
+    let file = File::create(file_path)
         .with_context(|| format!("Failed to create file: {:?}", file_path))?;
     serde_json::to_writer_pretty(file, options)
         .with_context(|| format!("Failed to write to file: {:?}", file_path))?;
@@ -189,13 +608,21 @@ pub(crate) fn write_options_to_json(file_path: &PathBuf, options: &PomodoroOptio
 
 /// Gets the path to the options file next to the executable.
 ///
+/// The first existing file of `pomodoro_options.json`, `pomodoro_options.toml`,
+/// `pomodoro_options.yaml` and `pomodoro_options.yml` is used. If none exists, the path of the
+/// JSON file is returned, which is where the default options are written.
+///
 /// # Errors
 ///
 /// Returns an error if there are any errors during the process of getting the file path.
 fn get_filepath_options_next_to_executable() -> Result<PathBuf> {
-    let filename = "pomodoro_options.json";
-    let mut path = get_folderpath_executable()?;
-    path.push(filename);
+    let folder = get_folderpath_executable()?;
+    let path = OptionsFormat::ALL
+        .iter()
+        .flat_map(|format| format.extensions())
+        .map(|extension| folder.join(format!("pomodoro_options.{}", extension)))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| folder.join("pomodoro_options.json"));
     Ok(path)
 }
 
@@ -204,7 +631,7 @@ fn get_filepath_options_next_to_executable() -> Result<PathBuf> {
 /// # Errors
 ///
 /// Returns an error if there are any errors during the process of getting the folder path.
-fn get_folderpath_executable() -> Result<PathBuf> {
+pub(crate) fn get_folderpath_executable() -> Result<PathBuf> {
     let exe_path = env::current_exe().context("Failed to get executable path.")?;
     let mut file_path = exe_path.clone();
     // Remove the executable name, keep the folder path.
@@ -214,17 +641,294 @@ fn get_folderpath_executable() -> Result<PathBuf> {
 
 #[test]
 fn test_read_options_from_json() {
-    // Test case for `read_options_from_json` function.
+    // Test case for `read_options_from_file` function with a JSON file.
     let filepath_test_json = get_project_root()
         .unwrap()
         .join("tests")
         .join("data")
         .join("pomodoro_options.json");
     // Assuming you have a valid JSON file with the correct structure
-    let options = read_options_from_json(Some(filepath_test_json)).unwrap();
+    let options = read_options_from_file(Some(filepath_test_json)).unwrap();
 
     assert_eq!(options.duration_pomodoro, 25);
     assert_eq!(options.additional_duration, 5);
     assert_eq!(options.duration_short_break, 5);
     assert_eq!(options.duration_long_break, 15);
+    // Files of earlier versions play the end event of the Pomodoro when a break ends.
+    assert!(matches!(
+        options.end_event(Phase::ShortBreak),
+        EndEvent::Sound { .. }
+    ));
+    assert!(matches!(
+        options.end_event(Phase::LongBreak),
+        EndEvent::Sound { .. }
+    ));
+    assert!(matches!(
+        options.start_event(Phase::Pomodoro),
+        EndEvent::None
+    ));
+}
+
+#[test]
+fn test_read_options_from_file() {
+    // The default profile is read from every format.
+    let folder_test_data = get_project_root().unwrap().join("tests").join("data");
+    for filename in [
+        "pomodoro_profiles.json",
+        "pomodoro_profiles.toml",
+        "pomodoro_profiles.yaml",
+    ] {
+        let options = read_options_from_file(Some(folder_test_data.join(filename))).unwrap();
+        assert_eq!(options.duration_pomodoro, 25, "{}", filename);
+        assert_eq!(options.duration_short_break, 5, "{}", filename);
+        assert_eq!(options.duration_long_break, 20, "{}", filename);
+    }
+}
+
+#[test]
+fn test_separate_events_of_phases() {
+    let options: PomodoroOptions = serde_json::from_str(
+        r#"{
+            "startEventPomodoro": {"command": {"program": "dnd-on"}},
+            "endEventPomodoro": {"sound": {"builtin": "bell"}},
+            "startEventShortBreak": {"command": {"program": "dnd-off"}},
+            "endEventShortBreak": {"sound": {"builtin": "chime"}},
+            "endEventLongBreak": "none"
+        }"#,
+    )
+    .unwrap();
+
+    assert!(matches!(
+        options.start_event(Phase::Pomodoro),
+        EndEvent::Command { program, .. } if program == "dnd-on"
+    ));
+    assert!(matches!(
+        options.start_event(Phase::ShortBreak),
+        EndEvent::Command { program, .. } if program == "dnd-off"
+    ));
+    assert!(matches!(
+        options.start_event(Phase::AdditionalPomodoro),
+        EndEvent::None
+    ));
+    assert!(matches!(
+        options.start_event(Phase::LongBreak),
+        EndEvent::None
+    ));
+    assert!(matches!(
+        options.end_event(Phase::Pomodoro),
+        EndEvent::Sound {
+            builtin: Some(BuiltinSound::Bell),
+            ..
+        }
+    ));
+    assert!(matches!(
+        options.end_event(Phase::ShortBreak),
+        EndEvent::Sound {
+            builtin: Some(BuiltinSound::Chime),
+            ..
+        }
+    ));
+    assert!(matches!(
+        options.end_event(Phase::LongBreak),
+        EndEvent::None
+    ));
+    assert!(matches!(
+        options.end_event(Phase::AdditionalPomodoro),
+        EndEvent::LockScreen
+    ));
+
+    let options: PomodoroOptions = serde_json::from_str(
+        r#"{
+            "timedEvents": [
+                {"at": {"secondsBeforeEnd": 60}, "event": {"sound": {"builtin": "chime"}}},
+                {"at": {"fraction": 0.5}, "event": "none"},
+                {"phase": "shortBreak", "at": {"secondsBeforeEnd": 30}, "event": "none"}
+            ]
+        }"#,
+    )
+    .unwrap();
+    let timed_events = options.timed_events_of(Phase::Pomodoro, Duration::from_secs(600));
+    assert_eq!(timed_events.len(), 2);
+    assert_eq!(timed_events[0].0, Duration::from_secs(540));
+    assert_eq!(timed_events[1].0, Duration::from_secs(300));
+    assert_eq!(
+        options
+            .timed_events_of(Phase::ShortBreak, Duration::from_secs(300))
+            .len(),
+        1
+    );
+    assert!(options
+        .timed_events_of(Phase::LongBreak, Duration::from_secs(900))
+        .is_empty());
+
+    let options = PomodoroOptions {
+        end_event_long_break: Some(EndEvent::Sequence(vec![EndEvent::Sound {
+            filepath_sound: PathBuf::from("does_not_exist.wav"),
+            builtin: None,
+        }])),
+        ..Default::default()
+    };
+    assert!(matches!(
+        options.verify(),
+        Err(VerificationError::InvalidSoundFile)
+    ));
+}
+
+#[test]
+fn test_verify() {
+    assert!(PomodoroOptions::default().verify().is_ok());
+    let options = PomodoroOptions {
+        duration_pomodoro: 0,
+        ..Default::default()
+    };
+    assert!(matches!(
+        options.verify(),
+        Err(VerificationError::InvalidDuration)
+    ));
+    let options = PomodoroOptions {
+        interval_long_break: 0,
+        ..Default::default()
+    };
+    assert!(matches!(
+        options.verify(),
+        Err(VerificationError::InvalidLongBreakInterval)
+    ));
+    let mut options = PomodoroOptions::default();
+    options.key_bindings.extend_minutes = MAX_MINUTES + 1;
+    assert!(matches!(
+        options.verify(),
+        Err(VerificationError::InvalidKeyBindingMinutes)
+    ));
+    options.key_bindings.extend_minutes = u64::MAX;
+    assert!(matches!(
+        options.verify(),
+        Err(VerificationError::InvalidKeyBindingMinutes)
+    ));
+}
+
+#[test]
+fn test_read_profile_from_file() {
+    let filepath_test_json = get_project_root()
+        .unwrap()
+        .join("tests")
+        .join("data")
+        .join("pomodoro_profiles.json");
+
+    // The default profile is used if no profile is given.
+    let options = read_profile_from_file(Some(filepath_test_json.clone()), None).unwrap();
+    assert_eq!(options.duration_pomodoro, 25);
+    assert_eq!(options.duration_short_break, 5);
+
+    // Unspecified fields are inherited from the base section, not from the defaults.
+    let options = read_profile_from_file(Some(filepath_test_json.clone()), Some("coding")).unwrap();
+    assert_eq!(options.duration_pomodoro, 50);
+    assert_eq!(options.duration_short_break, 10);
+    assert_eq!(options.duration_long_break, 20);
+    assert!(!options.auto_start_break);
+
+    let options = read_profile_from_file(Some(filepath_test_json.clone()), Some("study")).unwrap();
+    assert_eq!(options.duration_pomodoro, 40);
+    assert_eq!(options.interval_long_break, 3);
+    assert_eq!(options.duration_long_break, 20);
+
+    let error = read_profile_from_file(Some(filepath_test_json), Some("gaming")).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<PomodoroOptionsError>(),
+        Some(PomodoroOptionsError::ProfileNotFound(name)) if name == "gaming"
+    ));
+}
+
+#[test]
+fn test_read_profile_from_toml_and_yaml() {
+    let folder_test_data = get_project_root().unwrap().join("tests").join("data");
+    for filename in ["pomodoro_profiles.toml", "pomodoro_profiles.yaml"] {
+        let filepath_options = folder_test_data.join(filename);
+        let options = read_profile_from_file(Some(filepath_options.clone()), None).unwrap();
+        assert_eq!(options.duration_pomodoro, 25);
+        assert_eq!(options.duration_short_break, 5);
+        let options = read_profile_from_file(Some(filepath_options), Some("study")).unwrap();
+        assert_eq!(options.duration_pomodoro, 40);
+        assert_eq!(options.interval_long_break, 3);
+        assert_eq!(options.duration_long_break, 20);
+        assert!(!options.auto_start_break);
+    }
+
+    let error = read_options_from_file(Some(folder_test_data.join("pomodoro_options.ini")));
+    assert!(matches!(
+        error.unwrap_err().downcast_ref::<PomodoroOptionsError>(),
+        Some(PomodoroOptionsError::UnsupportedFormat(_))
+    ));
+}
+
+#[test]
+fn test_convert_options_file() {
+    let folder = std::env::temp_dir().join(format!("pomodoro_convert_test_{}", std::process::id()));
+    std::fs::create_dir_all(&folder).unwrap();
+    let filepath_json = folder.join("pomodoro_options.json");
+    write_options_to_json(&filepath_json, &PomodoroOptions::default()).unwrap();
+
+    // The default options contain nested events and fields set to null.
+    for extension in ["toml", "yaml", "json"] {
+        let filepath_output = folder.join(format!("converted.{}", extension));
+        convert_options_file(&filepath_json, &filepath_output, false).unwrap();
+        let options = read_options_from_file(Some(filepath_output)).unwrap();
+        assert_eq!(
+            serde_json::to_value(&options).unwrap(),
+            serde_json::to_value(PomodoroOptions::default()).unwrap()
+        );
+    }
+
+    // The profiles are kept and an existing file is only replaced on request.
+    let filepath_profiles = get_project_root()
+        .unwrap()
+        .join("tests")
+        .join("data")
+        .join("pomodoro_profiles.json");
+    let filepath_output = folder.join("converted.toml");
+    let error = convert_options_file(&filepath_profiles, &filepath_output, false).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<PomodoroOptionsError>(),
+        Some(PomodoroOptionsError::FileExists(_))
+    ));
+    convert_options_file(&filepath_profiles, &filepath_output, true).unwrap();
+    let options = read_profile_from_file(Some(filepath_output), Some("coding")).unwrap();
+    assert_eq!(options.duration_pomodoro, 50);
+    assert_eq!(options.duration_long_break, 20);
+
+    std::fs::remove_dir_all(folder).unwrap();
+}
+
+#[test]
+fn test_convert_every_end_event() {
+    let folder = std::env::temp_dir().join(format!("pomodoro_events_test_{}", std::process::id()));
+    std::fs::create_dir_all(&folder).unwrap();
+    let filepath_json = get_project_root()
+        .unwrap()
+        .join("tests")
+        .join("data")
+        .join("pomodoro_end_events.json");
+    let mut expected = read_options_file(&filepath_json).unwrap();
+    remove_null_fields(&mut expected);
+
+    // Every format is converted from the previous one, so TOML is read and written as well.
+    let mut filepath_input = filepath_json;
+    for (step, extension) in ["toml", "yaml", "json", "toml"].into_iter().enumerate() {
+        let filepath_output = folder.join(format!("converted_{}.{}", step, extension));
+        convert_options_file(&filepath_input, &filepath_output, false).unwrap();
+        let mut converted = read_options_file(&filepath_output).unwrap();
+        remove_null_fields(&mut converted);
+        assert_eq!(converted, expected, "{}", extension);
+        for profile in [None, Some("quiet")] {
+            let options = read_profile_from_file(Some(filepath_output.clone()), profile).unwrap();
+            let original: PomodoroOptions =
+                serde_json::from_value(select_profile(expected.clone(), profile).unwrap()).unwrap();
+            assert_eq!(
+                serde_json::to_value(options).unwrap(),
+                serde_json::to_value(original).unwrap()
+            );
+        }
+        filepath_input = filepath_output;
+    }
+
+    std::fs::remove_dir_all(folder).unwrap();
 }
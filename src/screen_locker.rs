@@ -0,0 +1,254 @@
+//! This module contains the screen lockers used to lock the screen on Linux.
+//!
+//! Linux has no single way to lock the screen. Depending on the desktop session, the screen can be
+//! locked through logind, the freedesktop ScreenSaver D-Bus interface, `xdg-screensaver` or a
+//! standalone locker like `swaylock`. The lockers are tried one after another until one succeeds.
+use anyhow::{bail, Context, Result};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Represents a way to lock the screen on Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScreenLocker {
+    /// Lock the session through logind using `loginctl lock-session`.
+    ///
+    /// Note that logind only asks the desktop session to lock itself. If no locker listens to
+    /// logind (e.g. a bare window manager without `swayidle` or `xss-lock`), nothing happens,
+    /// although `loginctl` succeeds. Therefore it is the last of the detected screen lockers.
+    Logind,
+    /// Call `Lock` on the `org.freedesktop.ScreenSaver` D-Bus interface of the session bus.
+    FreedesktopScreenSaver,
+    /// Lock the screen using `xdg-screensaver lock`.
+    XdgScreensaver,
+    /// Lock the screen using `swaylock`.
+    Swaylock,
+    /// Lock the screen using `i3lock`.
+    I3lock,
+    /// Lock the screen using `xsecurelock`.
+    Xsecurelock,
+}
+
+/// Error type for screen locking errors.
+#[derive(Error, Debug)]
+pub enum LockScreenError {
+    /// The platform has no way to lock the screen.
+    #[error("Locking the screen is not supported on this platform.")]
+    UnsupportedPlatform,
+    /// Every screen locker failed. Contains the reason of every failed attempt.
+    #[error("None of the screen lockers could lock the screen: {}", .0.join(" | "))]
+    NoScreenLockerSucceeded(Vec<String>),
+}
+
+impl ScreenLocker {
+    /// Locks the screen with this screen locker.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the screen locker is not available or failed to lock the screen.
+    fn lock(&self) -> Result<()> {
+        match self {
+            ScreenLocker::Logind => run_command("loginctl", &["lock-session"]),
+            ScreenLocker::FreedesktopScreenSaver => lock_with_freedesktop_screen_saver(),
+            ScreenLocker::XdgScreensaver => run_command("xdg-screensaver", &["lock"]),
+            // `-f` detaches swaylock after the screen is locked.
+            ScreenLocker::Swaylock => run_command("swaylock", &["-f"]),
+            // i3lock forks into the background by default.
+            ScreenLocker::I3lock => run_command("i3lock", &[]),
+            // xsecurelock blocks until the screen is unlocked, so it is not waited for.
+            ScreenLocker::Xsecurelock => spawn_command("xsecurelock"),
+        }
+    }
+}
+
+/// Locks the screen on Linux by trying the given screen lockers in order.
+///
+/// If `screen_lockers` is empty, the order is detected from the desktop session using
+/// [`detect_screen_lockers`].
+///
+/// # Errors
+///
+/// Returns a `LockScreenError::NoScreenLockerSucceeded` containing the reason of every failed
+/// attempt if none of the screen lockers could lock the screen.
+pub fn lock_screen_on_linux(screen_lockers: &[ScreenLocker]) -> Result<()> {
+    let screen_lockers = if screen_lockers.is_empty() {
+        detect_screen_lockers(
+            std::env::var("XDG_SESSION_TYPE").ok().as_deref(),
+            std::env::var("XDG_CURRENT_DESKTOP").ok().as_deref(),
+        )
+    } else {
+        screen_lockers.to_vec()
+    };
+    let mut failures = Vec::new();
+    for screen_locker in screen_lockers {
+        debug!("Trying to lock the screen with {:?}.", screen_locker);
+        match screen_locker.lock() {
+            Ok(()) => {
+                info!("Locked the screen with {:?}.", screen_locker);
+                return Ok(());
+            }
+            Err(e) => {
+                debug!(
+                    "Failed to lock the screen with {:?}: {:#}",
+                    screen_locker, e
+                );
+                failures.push(format!("{:?}: {:#}", screen_locker, e));
+            }
+        }
+    }
+    Err(LockScreenError::NoScreenLockerSucceeded(failures).into())
+}
+
+/// Detects the order in which the screen lockers should be tried.
+///
+/// The freedesktop ScreenSaver interface is tried first as it hands the locking over to the
+/// desktop environment and fails if no desktop environment implements it. The standalone lockers
+/// are chosen by the session type and a locker matching the current desktop (e.g. `swaylock` on
+/// sway) is moved to the front. logind is tried last, because it succeeds even if nothing locks
+/// the screen.
+///
+/// # Arguments
+///
+/// * `session_type` - The value of `XDG_SESSION_TYPE`, e.g. `wayland` or `x11`.
+/// * `current_desktop` - The value of `XDG_CURRENT_DESKTOP`, e.g. `GNOME` or `sway`.
+pub fn detect_screen_lockers(
+    session_type: Option<&str>,
+    current_desktop: Option<&str>,
+) -> Vec<ScreenLocker> {
+    let mut screen_lockers = vec![ScreenLocker::FreedesktopScreenSaver];
+    match session_type {
+        Some("wayland") => screen_lockers.push(ScreenLocker::Swaylock),
+        Some("x11") => screen_lockers.extend([
+            ScreenLocker::XdgScreensaver,
+            ScreenLocker::I3lock,
+            ScreenLocker::Xsecurelock,
+        ]),
+        _ => screen_lockers.extend([
+            ScreenLocker::XdgScreensaver,
+            ScreenLocker::Swaylock,
+            ScreenLocker::I3lock,
+            ScreenLocker::Xsecurelock,
+        ]),
+    }
+    let current_desktop = current_desktop.unwrap_or_default().to_lowercase();
+    let native_screen_locker = if current_desktop.contains("sway") {
+        Some(ScreenLocker::Swaylock)
+    } else if current_desktop.contains("i3") {
+        Some(ScreenLocker::I3lock)
+    } else {
+        None
+    };
+    if let Some(native_screen_locker) = native_screen_locker {
+        screen_lockers.retain(|screen_locker| *screen_locker != native_screen_locker);
+        screen_lockers.insert(0, native_screen_locker);
+    }
+    screen_lockers.push(ScreenLocker::Logind);
+    screen_lockers
+}
+
+/// Locks the screen by calling `Lock` on the `org.freedesktop.ScreenSaver` D-Bus interface.
+///
+/// KDE exports the interface at `/ScreenSaver`, other implementations at
+/// `/org/freedesktop/ScreenSaver`, so both object paths are tried.
+#[cfg(target_os = "linux")]
+fn lock_with_freedesktop_screen_saver() -> Result<()> {
+    let connection =
+        zbus::blocking::Connection::session().context("Failed to connect to the session bus.")?;
+    let lock = |path: &str| {
+        connection.call_method(
+            Some("org.freedesktop.ScreenSaver"),
+            path,
+            Some("org.freedesktop.ScreenSaver"),
+            "Lock",
+            &(),
+        )
+    };
+    lock("/org/freedesktop/ScreenSaver")
+        .or_else(|_| lock("/ScreenSaver"))
+        .context("Failed to call `Lock` on org.freedesktop.ScreenSaver.")?;
+    Ok(())
+}
+
+/// The freedesktop ScreenSaver interface is only available on Linux.
+#[cfg(not(target_os = "linux"))]
+fn lock_with_freedesktop_screen_saver() -> Result<()> {
+    Err(LockScreenError::UnsupportedPlatform.into())
+}
+
+/// Runs the command and waits for it to finish.
+///
+/// # Errors
+///
+/// Returns an error if the command could not be started or exited with a non-zero status.
+fn run_command(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run `{}`.", program))?;
+    if !output.status.success() {
+        bail!(
+            "`{}` exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Spawns the command without waiting for it to finish.
+///
+/// The command is given a short moment to fail, e.g. because no display is available. If it keeps
+/// running, it is waited for in a separate thread, so it does not remain as zombie process.
+///
+/// # Errors
+///
+/// Returns an error if the command could not be started or exited with a non-zero status right away.
+fn spawn_command(program: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .spawn()
+        .with_context(|| format!("Failed to run `{}`.", program))?;
+    thread::sleep(Duration::from_millis(500));
+    match child.try_wait()? {
+        Some(status) if !status.success() => bail!("`{}` exited with {}.", program, status),
+        Some(_) => (),
+        None => {
+            let program = program.to_string();
+            thread::Builder::new()
+                .name("screen_locker".to_string())
+                .spawn(move || match child.wait() {
+                    Ok(status) => debug!("`{}` exited with {}.", program, status),
+                    Err(e) => debug!("Failed to wait for `{}`: {}", program, e),
+                })
+                .context("Failed to spawn the thread waiting for the screen locker.")?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_detect_screen_lockers() {
+    assert_eq!(
+        detect_screen_lockers(Some("wayland"), Some("sway")),
+        vec![
+            ScreenLocker::Swaylock,
+            ScreenLocker::FreedesktopScreenSaver,
+            ScreenLocker::Logind
+        ]
+    );
+    assert_eq!(
+        detect_screen_lockers(Some("x11"), Some("GNOME")),
+        vec![
+            ScreenLocker::FreedesktopScreenSaver,
+            ScreenLocker::XdgScreensaver,
+            ScreenLocker::I3lock,
+            ScreenLocker::Xsecurelock,
+            ScreenLocker::Logind
+        ]
+    );
+    assert_eq!(detect_screen_lockers(None, None).len(), 6);
+}
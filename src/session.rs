@@ -0,0 +1,781 @@
+//! This module contains the `PomodoroSession` which runs the cycle of Pomodoros and breaks.
+//!
+//! A session notifies about phase changes through callbacks and channels, so the timer can be
+//! embedded into other tools.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use locking_pomodoro_timer::pomodoro_options::PomodoroOptions;
+//! use locking_pomodoro_timer::{start_input_stream, PhaseEvent, PomodoroSession};
+//!
+//! let mut session = PomodoroSession::new(PomodoroOptions::default());
+//! session.on_phase_event(|event| {
+//!     if let PhaseEvent::Started { phase, .. } = event {
+//!         println!("{} started.", phase);
+//!     }
+//! });
+//! let phase_events = session.subscribe();
+//! std::thread::spawn(move || {
+//!     for event in phase_events {
+//!         log::info!("{:?}", event);
+//!     }
+//! });
+//! session.run(&start_input_stream());
+//! ```
+use crate::cli_utilities::{
+    ask_for_label, ask_for_new_pomodoro, execute_timer, help_paused, time_with_progress_bar,
+};
+use crate::clock::{Clock, SystemClock};
+use crate::end_events::{start_end_event, EndEvent, EndEventContext};
+use crate::history::{
+    append_record, get_filepath_history, read_records, recent_labels, HistoryRecord,
+};
+use crate::key_bindings::Action;
+use crate::message_creator::{
+    generate_print_message_before_additional_break, generate_print_message_before_break,
+    generate_print_message_before_pomodoro,
+};
+use crate::persistence::{remove_state, SavedState, StateSaver};
+use crate::pomo_info::PomoInfo;
+use crate::pomodoro_options::PomodoroOptions;
+use crate::simulation::{EndEventRecorder, EventTrigger};
+use crate::state_machine::{CycleAction, CycleEvent, PomodoroStateMachine};
+use crate::timer::Timer;
+use crate::view::{TerminalView, View};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Represents the phases of a Pomodoro cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Phase {
+    /// The focus phase of a Pomodoro.
+    Pomodoro,
+    /// The additional duration after a Pomodoro, e.g. to finish the current thought.
+    AdditionalPomodoro,
+    /// A short break.
+    ShortBreak,
+    /// A long break, taken after `interval_long_break` Pomodoros.
+    LongBreak,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Phase::Pomodoro => "Pomodoro",
+            Phase::AdditionalPomodoro => "Additional Pomodoro",
+            Phase::ShortBreak => "Short break",
+            Phase::LongBreak => "Long break",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Represents an event emitted by a `PomodoroSession` when a phase changes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PhaseEvent {
+    /// A phase has started.
+    Started {
+        /// The phase which has started.
+        phase: Phase,
+        /// The planned duration of the phase.
+        duration: Duration,
+        /// The number of Pomodoros completed in this session.
+        counter: i32,
+    },
+    /// A phase has run for its full duration.
+    Finished {
+        /// The phase which has finished.
+        phase: Phase,
+        /// The number of Pomodoros completed in this session.
+        counter: i32,
+    },
+    /// A phase was quit by the user before it was finished.
+    Quit {
+        /// The phase which was quit.
+        phase: Phase,
+        /// The number of Pomodoros completed in this session.
+        counter: i32,
+    },
+    /// The session has ended because the user did not want to repeat the timer or quit the
+    /// program.
+    SessionEnded {
+        /// The number of Pomodoros completed in this session.
+        counter: i32,
+    },
+}
+
+/// Represents how a `PomodoroSession` has ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionOutcome {
+    /// The user did not want to repeat the timer, the Pomodoro limit was reached or no input can
+    /// be received anymore.
+    Ended,
+    /// The user quit the program, e.g. by pressing ctrl+c. The state of an interrupted phase is
+    /// kept, so it can be resumed.
+    Quit,
+}
+
+/// Represents a snapshot of the state of a session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStatus {
+    /// The running phase or `None` if no timer is running, e.g. while waiting for the user.
+    pub phase: Option<Phase>,
+    /// The elapsed time of the running phase in seconds, including skipped time.
+    pub elapsed_seconds: u64,
+    /// The remaining time of the running phase in seconds.
+    pub remaining_seconds: u64,
+    /// Indicates whether the timer of the running phase is paused.
+    pub paused: bool,
+    /// The number of Pomodoros completed in this session.
+    pub counter: i32,
+    /// The label of the task worked on, if any.
+    pub label: Option<String>,
+}
+
+/// A handle to query the status of a running session from other threads.
+#[derive(Clone, Default)]
+pub struct StatusHandle {
+    current: Arc<Mutex<CurrentPhase>>,
+}
+
+/// The state of a session which is shared through a `StatusHandle`.
+#[derive(Default)]
+struct CurrentPhase {
+    running: Option<(Phase, Arc<Timer>)>,
+    counter: i32,
+    label: Option<String>,
+}
+
+impl StatusHandle {
+    /// Gets a snapshot of the status of the session.
+    pub fn status(&self) -> SessionStatus {
+        let current = self.current.lock().unwrap();
+        let (phase, elapsed, remaining, paused) = match &current.running {
+            Some((phase, timer)) => {
+                let elapsed = timer.get_elapsed_time();
+                (
+                    Some(*phase),
+                    elapsed,
+                    timer.get_duration().saturating_sub(elapsed),
+                    timer.is_paused(),
+                )
+            }
+            None => (None, Duration::ZERO, Duration::ZERO, false),
+        };
+        SessionStatus {
+            phase,
+            elapsed_seconds: elapsed.as_secs(),
+            remaining_seconds: remaining.as_secs(),
+            paused,
+            counter: current.counter,
+            label: current.label.clone(),
+        }
+    }
+
+    /// Updates the shared state of the session.
+    fn update(&self, running: Option<(Phase, Arc<Timer>)>, counter: i32, label: Option<String>) {
+        *self.current.lock().unwrap() = CurrentPhase {
+            running,
+            counter,
+            label,
+        };
+    }
+}
+
+/// A callback which is called for every `PhaseEvent`.
+type PhaseEventCallback = Box<dyn Fn(&PhaseEvent) + Send>;
+
+/// Represents a session of consecutive Pomodoros and breaks.
+pub struct PomodoroSession {
+    options: PomodoroOptions,
+    counter: i32,
+    label: Option<String>,
+    status: StatusHandle,
+    callbacks: Vec<PhaseEventCallback>,
+    senders: Vec<Sender<PhaseEvent>>,
+    filepath_state: Option<PathBuf>,
+    resumed: Option<SavedState>,
+    clock: Arc<dyn Clock>,
+    recorder: Option<EndEventRecorder>,
+    keep_history: bool,
+    pomodoro_limit: Option<i32>,
+    view: Arc<dyn View>,
+}
+
+impl PomodoroSession {
+    /// Creates a new `PomodoroSession` with the given options.
+    pub fn new(options: PomodoroOptions) -> Self {
+        PomodoroSession {
+            options,
+            counter: 0,
+            label: None,
+            status: StatusHandle::default(),
+            callbacks: Vec::new(),
+            senders: Vec::new(),
+            filepath_state: None,
+            resumed: None,
+            clock: Arc::new(SystemClock),
+            recorder: None,
+            keep_history: true,
+            pomodoro_limit: None,
+            view: Arc::new(TerminalView::default()),
+        }
+    }
+
+    /// Gets the options of the session.
+    pub fn options(&self) -> &PomodoroOptions {
+        &self.options
+    }
+
+    /// Gets the number of Pomodoros completed in this session.
+    pub fn counter(&self) -> i32 {
+        self.counter
+    }
+
+    /// Gets the label of the task currently worked on, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Sets the label of the task currently worked on.
+    ///
+    /// The label is stored in the history records of the following phases.
+    pub fn set_label(&mut self, label: Option<String>) {
+        self.label = label;
+    }
+
+    /// Saves the state of the running session periodically to the file, so an interrupted session
+    /// can be resumed with `resume`. The file is removed when the session ends regularly.
+    pub fn save_state_to(&mut self, filepath_state: PathBuf) {
+        self.filepath_state = Some(filepath_state);
+    }
+
+    /// Resumes an interrupted session when it is run.
+    ///
+    /// The session starts with the saved phase, elapsed time, pause state and counter. The saved
+    /// label is used unless a label has already been set.
+    pub fn resume(&mut self, state: SavedState) {
+        self.counter = state.counter;
+        if self.label.is_none() {
+            self.label = state.label.clone();
+        }
+        self.resumed = Some(state);
+    }
+
+    /// Sets the clock which measures the phases. By default, the system clock is used.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Records the end events with the recorder instead of starting them, e.g. in a simulation.
+    pub fn record_end_events(&mut self, recorder: EndEventRecorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Sets whether the phases are appended to the history. This is the default.
+    pub fn set_keep_history(&mut self, keep_history: bool) {
+        self.keep_history = keep_history;
+    }
+
+    /// Ends the session after the given number of Pomodoros instead of asking for the next one.
+    /// If `None`, the session runs until the user quits it.
+    pub fn set_pomodoro_limit(&mut self, pomodoro_limit: Option<i32>) {
+        self.pomodoro_limit = pomodoro_limit;
+    }
+
+    /// Sets the view which shows the session. By default, it is shown line by line on the
+    /// terminal with a progress bar, see `TerminalView`.
+    pub fn set_view(&mut self, view: Arc<dyn View>) {
+        self.view = view;
+    }
+
+    /// Gets the view which shows the session, e.g. to show messages of other interfaces.
+    pub fn view(&self) -> Arc<dyn View> {
+        self.view.clone()
+    }
+
+    /// Gets a handle to query the status of the session while it is running.
+    pub fn status_handle(&self) -> StatusHandle {
+        self.status.clone()
+    }
+
+    /// Registers a callback which is called for every `PhaseEvent`.
+    ///
+    /// The callback is called on the thread running the session, so it should return quickly.
+    pub fn on_phase_event<F: Fn(&PhaseEvent) + Send + 'static>(&mut self, callback: F) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Creates a channel which receives every `PhaseEvent` of the session.
+    ///
+    /// # Returns
+    ///
+    /// A Receiver for the phase events. The channel is closed when the session is dropped.
+    pub fn subscribe(&mut self) -> Receiver<PhaseEvent> {
+        let (sender, receiver) = channel();
+        self.senders.push(sender);
+        receiver
+    }
+
+    /// Runs the session until the user quits it.
+    ///
+    /// The function returns instead of exiting the program, also when the user quits the program,
+    /// so `PhaseEvent::SessionEnded` is always sent. Restoring the terminal and exiting the program
+    /// is left to the caller.
+    ///
+    /// The session is rendered by its view, see `set_view`. The timer can be paused, resumed,
+    /// quit and skipped by sending the keys `p`, `r`, `q` and `s` through the `receiver`, which is
+    /// usually created by `start_input_stream`. The task label can be changed with `l`. These are
+    /// the default keys, which can be changed with the option `key_bindings`. The commands
+    /// `pause`, `resume`, `skip N`, `quit` and `confirm` always work, see `key_bindings`.
+    ///
+    /// # Arguments
+    ///
+    /// * `receiver` - The receiver for input events.
+    ///
+    /// # Returns
+    ///
+    /// How the session has ended.
+    pub fn run(&mut self, receiver: &Receiver<String>) -> SessionOutcome {
+        let mut state_machine = PomodoroStateMachine::new(&self.options);
+        let mut label = self.label.clone();
+        // The phase before asking for a new Pomodoro, used for the reminder.
+        let mut last_phase = Phase::Pomodoro;
+        let mut resumed = self.resumed.take();
+        let mut actions: VecDeque<CycleAction> = match &resumed {
+            Some(state) => state_machine.resume(state.phase, state.counter),
+            None => state_machine.handle(CycleEvent::Start),
+        }
+        .into();
+        let mut outcome = SessionOutcome::Ended;
+        let mut state_saver = self.filepath_state.as_ref().and_then(|filepath_state| {
+            StateSaver::start(self.status.clone(), filepath_state.clone())
+                .map_err(|e| log::error!("{:#}", e))
+                .ok()
+        });
+        while let Some(action) = actions.pop_front() {
+            let limit_reached = self
+                .pomodoro_limit
+                .is_some_and(|limit| state_machine.counter() >= limit);
+            let event = match action {
+                CycleAction::StartPhase {
+                    phase: Phase::Pomodoro,
+                    ..
+                } if limit_reached => {
+                    actions = VecDeque::from([CycleAction::End]);
+                    None
+                }
+                CycleAction::AskForPomodoro if limit_reached => Some(CycleEvent::Decline),
+                CycleAction::StartPhase {
+                    phase,
+                    duration,
+                    pomo_info,
+                } => match self.run_phase(
+                    phase,
+                    duration,
+                    &pomo_info,
+                    state_machine.counter(),
+                    &mut label,
+                    resumed.take(),
+                    state_saver.as_ref(),
+                    receiver,
+                ) {
+                    ControlFlow::Continue(event) => Some(event),
+                    ControlFlow::Break(phase_outcome) => {
+                        outcome = phase_outcome;
+                        actions = VecDeque::from([CycleAction::End]);
+                        None
+                    }
+                },
+                CycleAction::StartEndEvent {
+                    phase,
+                    next_phase,
+                    pomo_info,
+                } => {
+                    let context = EndEventContext {
+                        phase,
+                        next_phase,
+                        pomo_info,
+                        counter: state_machine.counter(),
+                        label: label.clone(),
+                    };
+                    self.start_event(EventTrigger::End, self.options.end_event(phase), &context);
+                    last_phase = phase;
+                    None
+                }
+                CycleAction::AskForBreak { phase, duration } => {
+                    let key_bindings = &self.options.key_bindings;
+                    let description = format!(
+                        "start the {} of {:.0} minutes",
+                        phase.to_string().to_lowercase(),
+                        duration.as_secs() / 60
+                    );
+                    self.view.show_message(&format!(
+                        "Press {}.",
+                        key_bindings.help(&[(Action::Confirm, &description)])
+                    ));
+                    loop {
+                        let Ok(pressed_key) = receiver.recv() else {
+                            log::debug!("No input can be received anymore.");
+                            actions = VecDeque::from([CycleAction::End]);
+                            break None;
+                        };
+                        match key_bindings.action(&pressed_key) {
+                            Some(Action::Confirm) => break Some(CycleEvent::Confirm),
+                            Some(Action::QuitApp) => {
+                                outcome = SessionOutcome::Quit;
+                                actions = VecDeque::from([CycleAction::End]);
+                                break None;
+                            }
+                            _ => log::debug!("Invalid input: {}", pressed_key),
+                        }
+                    }
+                }
+                CycleAction::AskForPomodoro => {
+                    let context = EndEventContext {
+                        phase: last_phase,
+                        next_phase: Phase::Pomodoro,
+                        pomo_info: state_machine.pomo_info(),
+                        counter: state_machine.counter(),
+                        label: label.clone(),
+                    };
+                    match ask_for_new_pomodoro(
+                        receiver,
+                        &self.options,
+                        &context,
+                        self.clock.as_ref(),
+                        self.recorder.as_ref(),
+                        &self.view,
+                    ) {
+                        ControlFlow::Continue(input) if input.trim().is_empty() => {
+                            Some(CycleEvent::Confirm)
+                        }
+                        ControlFlow::Continue(_) => Some(CycleEvent::Decline),
+                        ControlFlow::Break(quit_outcome) => {
+                            outcome = quit_outcome;
+                            actions = VecDeque::from([CycleAction::End]);
+                            None
+                        }
+                    }
+                }
+                CycleAction::End => {
+                    // The saver is stopped before the status is cleared, so the state of a phase
+                    // interrupted by quitting the program is kept to be resumed.
+                    if let Some(state_saver) = state_saver.take() {
+                        state_saver.stop();
+                    }
+                    self.status
+                        .update(None, state_machine.counter(), label.clone());
+                    self.notify(PhaseEvent::SessionEnded {
+                        counter: state_machine.counter(),
+                    });
+                    None
+                }
+            };
+            if let Some(event) = event {
+                actions.extend(state_machine.handle(event));
+            }
+        }
+        self.counter = state_machine.counter();
+        self.label = label;
+        if let Some(state_saver) = state_saver {
+            state_saver.stop();
+        }
+        if outcome == SessionOutcome::Ended {
+            if let Some(filepath_state) = &self.filepath_state {
+                if let Err(e) = remove_state(filepath_state) {
+                    log::error!("{:#}", e);
+                }
+            }
+        }
+        outcome
+    }
+
+    /// Runs a single phase and notifies about its start and end.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - The phase to run.
+    /// * `duration` - The duration of the phase.
+    /// * `pomo_info` - The information about the current Pomodoro.
+    /// * `counter` - The number of completed Pomodoros.
+    /// * `label` - The label of the current task, which can be changed during the phase.
+    /// * `resumed` - The saved state if the phase is resumed after an interruption.
+    /// * `state_saver` - The saver of the state, if the state is saved.
+    /// * `receiver` - The receiver for input events.
+    ///
+    /// # Returns
+    ///
+    /// The event to report to the state machine or the outcome of the session if it has to end,
+    /// because the user quit the program or no input can be received anymore while asking for
+    /// the label.
+    #[allow(clippy::too_many_arguments)]
+    fn run_phase(
+        &self,
+        phase: Phase,
+        duration: Duration,
+        pomo_info: &PomoInfo,
+        counter: i32,
+        label: &mut Option<String>,
+        resumed: Option<SavedState>,
+        state_saver: Option<&StateSaver>,
+        receiver: &Receiver<String>,
+    ) -> ControlFlow<SessionOutcome, CycleEvent> {
+        let recent_labels = self.recent_labels();
+        if phase == Phase::Pomodoro && self.options.ask_for_label && resumed.is_none() {
+            *label = ask_for_label(
+                receiver,
+                label.as_deref(),
+                &recent_labels,
+                self.view.as_ref(),
+            )?;
+        }
+        let print_message = match phase {
+            Phase::Pomodoro => {
+                generate_print_message_before_pomodoro(pomo_info, &self.options, label.as_deref())
+            }
+            Phase::AdditionalPomodoro => generate_print_message_before_additional_break(
+                pomo_info,
+                &self.options,
+                label.as_deref(),
+            ),
+            Phase::ShortBreak | Phase::LongBreak => {
+                generate_print_message_before_break(pomo_info, &self.options, label.as_deref())
+            }
+        };
+        self.view.show_phase_info(&print_message);
+        // The context of the start and timed events has the running phase as both phases.
+        let context = EndEventContext {
+            phase,
+            next_phase: phase,
+            pomo_info: pomo_info.clone(),
+            counter,
+            label: label.clone(),
+        };
+        self.start_event(
+            EventTrigger::Start,
+            self.options.start_event(phase),
+            &context,
+        );
+        let timer = Timer::with_clock(duration, self.clock.clone());
+        let timer = match &resumed {
+            Some(state) => {
+                let timer = timer.with_elapsed_time(Duration::from_secs(state.elapsed_seconds));
+                if state.paused {
+                    timer.pause();
+                    self.view.show_message(&format!(
+                        "The resumed timer is paused. {}",
+                        help_paused(&self.options.key_bindings)
+                    ));
+                }
+                Arc::new(timer)
+            }
+            None => Arc::new(timer),
+        };
+        self.status
+            .update(Some((phase, timer.clone())), counter, label.clone());
+        if let Some(state_saver) = state_saver {
+            state_saver.update();
+        }
+        self.notify(PhaseEvent::Started {
+            phase,
+            duration,
+            counter,
+        });
+        // The additional Pomodoro directly continues the Pomodoro, so "Times up!" is not printed.
+        let record = if phase == Phase::AdditionalPomodoro {
+            time_with_progress_bar(
+                phase,
+                &timer,
+                receiver,
+                label,
+                &recent_labels,
+                &self.options,
+                &context,
+                self.recorder.as_ref(),
+                &self.view,
+            )
+        } else {
+            execute_timer(
+                phase,
+                &timer,
+                receiver,
+                label,
+                &recent_labels,
+                &self.options,
+                &context,
+                self.recorder.as_ref(),
+                &self.view,
+            )
+        }?;
+        self.status.update(None, counter, label.clone());
+        // The finished phase must not be offered to be resumed after a crash at the next prompt.
+        if let Some(state_saver) = state_saver {
+            state_saver.update();
+        }
+        self.append_to_history(&record);
+        if record.quit {
+            self.notify(PhaseEvent::Quit { phase, counter });
+            ControlFlow::Continue(CycleEvent::PhaseQuit)
+        } else {
+            self.notify(PhaseEvent::Finished { phase, counter });
+            ControlFlow::Continue(CycleEvent::PhaseFinished)
+        }
+    }
+
+    /// Starts the event or records it if the end events are recorded.
+    fn start_event(&self, trigger: EventTrigger, event: &EndEvent, context: &EndEventContext) {
+        match &self.recorder {
+            Some(recorder) => recorder.record(trigger, event, context),
+            None => start_end_event(event, &self.options, context, &self.view),
+        }
+    }
+
+    /// Appends the record to the history file unless the history is disabled.
+    ///
+    /// Errors are logged and shown by the view, so that a broken history does not stop the timer.
+    fn append_to_history(&self, record: &HistoryRecord) {
+        if !self.keep_history {
+            return;
+        }
+        let result = get_filepath_history(&self.options.filepath_history)
+            .and_then(|filepath_history| append_record(&filepath_history, record));
+        if let Err(e) = result {
+            log::error!("Failed to append to the history: {:#}", e);
+            self.view.show_message(&format!("Error: {:#}", e));
+        }
+    }
+
+    /// Gets the recently used task labels from the history.
+    ///
+    /// Errors are logged, so that a broken history does not stop the timer.
+    fn recent_labels(&self) -> Vec<String> {
+        let records = get_filepath_history(&self.options.filepath_history)
+            .and_then(|filepath_history| read_records(&filepath_history));
+        match records {
+            Ok(records) => recent_labels(&records, 9),
+            Err(e) => {
+                log::error!("Failed to read the history: {:#}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Notifies the callbacks and subscribers about the phase event.
+    fn notify(&self, event: PhaseEvent) {
+        for callback in &self.callbacks {
+            callback(&event);
+        }
+        for sender in &self.senders {
+            // A dropped receiver is not an error, the subscriber is just not interested anymore.
+            let _ = sender.send(event.clone());
+        }
+    }
+}
+
+#[test]
+fn test_session_ends_without_input() {
+    let (sender, receiver) = channel::<String>();
+    drop(sender);
+    for ask_for_label in [true, false] {
+        let mut session = PomodoroSession::new(PomodoroOptions {
+            ask_for_label,
+            additional_duration: 0,
+            auto_start_break: false,
+            timed_events: vec![crate::end_events::TimedEvent {
+                phase: Phase::Pomodoro,
+                at: crate::end_events::EventTime::SecondsBeforeEnd(61),
+                event: EndEvent::LockScreen,
+            }],
+            ..PomodoroOptions::default()
+        });
+        let clock = Arc::new(crate::clock::VirtualClock::new());
+        let start = clock.now();
+        session.set_clock(clock.clone());
+        let recorder = EndEventRecorder::new(clock.clone());
+        session.record_end_events(recorder.clone());
+        session.set_keep_history(false);
+        let phase_events = session.subscribe();
+        assert_eq!(session.run(&receiver), SessionOutcome::Ended);
+
+        // Without input, the label can not be asked. Otherwise the Pomodoro runs until its end
+        // and the session ends when the break has to be confirmed.
+        let mut expected = vec![
+            PhaseEvent::Started {
+                phase: Phase::Pomodoro,
+                duration: Duration::from_secs(25 * 60),
+                counter: 0,
+            },
+            PhaseEvent::Finished {
+                phase: Phase::Pomodoro,
+                counter: 0,
+            },
+        ];
+        let mut duration = Duration::from_secs(25 * 60);
+        if ask_for_label {
+            expected.clear();
+            duration = Duration::ZERO;
+        }
+        expected.push(PhaseEvent::SessionEnded { counter: 0 });
+        assert_eq!(phase_events.try_iter().collect::<Vec<_>>(), expected);
+        // The closed input does not advance the clock, so the timed event and the Pomodoro end
+        // at their times.
+        assert_eq!(clock.now() - start, duration);
+        let end_events: Vec<Duration> = recorder.events().iter().map(|event| event.at).collect();
+        let expected_end_events = if ask_for_label {
+            vec![]
+        } else {
+            vec![Duration::from_secs(25 * 60 - 61), duration]
+        };
+        assert_eq!(end_events, expected_end_events);
+    }
+}
+
+#[test]
+fn test_session_returns_when_program_is_quit() {
+    let filepath_state = std::env::temp_dir()
+        .join(format!("pomodoro_quit_test_{}", std::process::id()))
+        .join("state.json");
+    let (sender, receiver) = channel::<String>();
+    sender.send("ctrl+c".to_string()).unwrap();
+    let mut session = PomodoroSession::new(PomodoroOptions {
+        ask_for_label: false,
+        ..PomodoroOptions::default()
+    });
+    let clock = Arc::new(crate::clock::VirtualClock::new());
+    session.set_clock(clock.clone());
+    session.record_end_events(EndEventRecorder::new(clock));
+    session.set_keep_history(false);
+    session.save_state_to(filepath_state.clone());
+    let phase_events = session.subscribe();
+    let outcome = session.run(&receiver);
+    let state = crate::persistence::read_state(&filepath_state);
+    std::fs::remove_dir_all(filepath_state.parent().unwrap()).unwrap();
+
+    assert_eq!(outcome, SessionOutcome::Quit);
+    // The subscribers are notified and the interrupted Pomodoro can be resumed.
+    assert_eq!(
+        phase_events.try_iter().collect::<Vec<_>>(),
+        vec![
+            PhaseEvent::Started {
+                phase: Phase::Pomodoro,
+                duration: Duration::from_secs(25 * 60),
+                counter: 0,
+            },
+            PhaseEvent::SessionEnded { counter: 0 },
+        ]
+    );
+    assert_eq!(
+        state.unwrap().map(|state| state.phase),
+        Some(Phase::Pomodoro)
+    );
+}
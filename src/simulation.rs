@@ -0,0 +1,384 @@
+//! This module simulates a session, so options can be verified without waiting for hours.
+//!
+//! The simulated session runs the same cycle as the timer on the terminal, but measures the time
+//! with a `VirtualClock`, which does not wait at all, or a `ScaledClock`, which runs faster than
+//! the system clock. All questions are answered with enter and the end events are recorded by an
+//! `EndEventRecorder` instead of being started. No history is written.
+//!
+//! # Examples
+//!
+//! ```
+//! use locking_pomodoro_timer::pomodoro_options::PomodoroOptions;
+//! use locking_pomodoro_timer::simulation::{simulate, Speed};
+//!
+//! let report = simulate(PomodoroOptions::default(), Speed::Virtual, 1);
+//! println!("{}", report);
+//! ```
+use crate::clock::{Clock, ScaledClock, VirtualClock};
+use crate::end_events::{EndEvent, EndEventContext};
+use crate::pomodoro_options::PomodoroOptions;
+use crate::session::{PhaseEvent, PomodoroSession};
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Represents the speed of a simulation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Speed {
+    /// The time passes without waiting.
+    Virtual,
+    /// The time passes faster than real time by the factor, e.g. 60 for a minute per second.
+    Factor(f64),
+}
+
+impl Speed {
+    /// Creates the clock running at the speed.
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        match self {
+            Speed::Virtual => Arc::new(VirtualClock::new()),
+            Speed::Factor(speed) => Arc::new(ScaledClock::new(*speed)),
+        }
+    }
+}
+
+/// Parses the speed of a simulation, e.g. `virtual`, `60x` or `60`.
+///
+/// # Errors
+///
+/// Returns an error if the speed is neither `virtual` nor a positive factor.
+pub fn parse_speed(speed: &str) -> Result<Speed> {
+    let speed = speed.trim();
+    if speed.eq_ignore_ascii_case("virtual") {
+        return Ok(Speed::Virtual);
+    }
+    let factor: f64 = speed
+        .strip_suffix(['x', 'X'])
+        .unwrap_or(speed)
+        .parse()
+        .with_context(|| {
+            format!(
+                "Invalid speed {:?}, expected e.g. `60x` or `virtual`",
+                speed
+            )
+        })?;
+    if !factor.is_finite() || factor <= 0.0 {
+        bail!("The speed must be positive, but is {}.", speed);
+    }
+    Ok(Speed::Factor(factor))
+}
+
+/// Represents what triggered an end event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventTrigger {
+    /// The start of a phase.
+    Start,
+    /// A timed event during a phase.
+    Timed,
+    /// The end of a phase.
+    End,
+    /// The reminder after a break.
+    Reminder,
+}
+
+impl fmt::Display for EventTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EventTrigger::Start => "Start event",
+            EventTrigger::Timed => "Timed event",
+            EventTrigger::End => "End event",
+            EventTrigger::Reminder => "Reminder",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Represents an end event which was recorded instead of being started.
+#[derive(Debug, Clone)]
+pub struct RecordedEndEvent {
+    /// The time since the recorder was created.
+    pub at: Duration,
+    /// What triggered the end event.
+    pub trigger: EventTrigger,
+    /// The end event.
+    pub event: EndEvent,
+    /// The context the end event would have been started with.
+    pub context: EndEventContext,
+}
+
+/// Records end events instead of starting them.
+///
+/// The recorder can be cloned cheaply, all clones share the recorded events.
+#[derive(Clone)]
+pub struct EndEventRecorder {
+    clock: Arc<dyn Clock>,
+    start: Instant,
+    events: Arc<Mutex<Vec<RecordedEndEvent>>>,
+}
+
+impl EndEventRecorder {
+    /// Creates a recorder which measures the time of the events with the clock.
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        EndEventRecorder {
+            start: clock.now(),
+            clock,
+            events: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Records the end event. The `none` event is not recorded.
+    ///
+    /// # Arguments
+    ///
+    /// * `trigger` - What triggered the end event.
+    /// * `event` - The end event.
+    /// * `context` - The context the end event would have been started with.
+    pub fn record(&self, trigger: EventTrigger, event: &EndEvent, context: &EndEventContext) {
+        if matches!(event, EndEvent::None) {
+            return;
+        }
+        let recorded = RecordedEndEvent {
+            at: self.clock.now().saturating_duration_since(self.start),
+            trigger,
+            event: event.clone(),
+            context: context.clone(),
+        };
+        self.events.lock().unwrap().push(recorded);
+    }
+
+    /// Gets the recorded end events in the order they were recorded.
+    pub fn events(&self) -> Vec<RecordedEndEvent> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+/// Represents the result of a simulation.
+#[derive(Debug, Clone)]
+pub struct SimulationReport {
+    /// The simulated time of the whole session.
+    pub duration: Duration,
+    /// The phase events with the time they were emitted at.
+    pub phase_events: Vec<(Duration, PhaseEvent)>,
+    /// The recorded end events.
+    pub end_events: Vec<RecordedEndEvent>,
+}
+
+impl fmt::Display for SimulationReport {
+    /// Prints the phase events and end events as a timeline.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // At the same time, a phase finishes, its end event starts, the next phase starts and
+        // then its start event.
+        let mut lines: Vec<(Duration, u8, String)> = self
+            .phase_events
+            .iter()
+            .map(|(at, event)| {
+                let rank = match event {
+                    PhaseEvent::Finished { .. } | PhaseEvent::Quit { .. } => 0,
+                    PhaseEvent::Started { .. } | PhaseEvent::SessionEnded { .. } => 2,
+                };
+                (*at, rank, describe_phase_event(event))
+            })
+            .collect();
+        lines.extend(self.end_events.iter().map(|recorded| {
+            let event = serde_json::to_string(&recorded.event).unwrap_or_default();
+            let rank = match recorded.trigger {
+                EventTrigger::End => 1,
+                EventTrigger::Start | EventTrigger::Timed | EventTrigger::Reminder => 3,
+            };
+            let description = match recorded.trigger {
+                EventTrigger::End => format!(
+                    "{} of {} (next: {}): {}",
+                    recorded.trigger, recorded.context.phase, recorded.context.next_phase, event
+                ),
+                _ => format!(
+                    "{} of {}: {}",
+                    recorded.trigger, recorded.context.phase, event
+                ),
+            };
+            (recorded.at, rank, description)
+        }));
+        lines.sort_by_key(|(at, rank, _)| (*at, *rank));
+        writeln!(f, "Simulated {}:", format_duration(self.duration))?;
+        for (at, _, description) in lines {
+            writeln!(f, "{:>9}  {}", format_duration(at), description)?;
+        }
+        Ok(())
+    }
+}
+
+/// Describes the phase event for the timeline.
+fn describe_phase_event(event: &PhaseEvent) -> String {
+    match event {
+        PhaseEvent::Started {
+            phase, duration, ..
+        } => format!("{} started ({} min)", phase, duration.as_secs() / 60),
+        PhaseEvent::Finished { phase, .. } => format!("{} finished", phase),
+        PhaseEvent::Quit { phase, .. } => format!("{} quit", phase),
+        PhaseEvent::SessionEnded { counter } => {
+            let pomodoros = if *counter == 1 {
+                "Pomodoro"
+            } else {
+                "Pomodoros"
+            };
+            format!("Session ended after {} {}", counter, pomodoros)
+        }
+    }
+}
+
+/// Formats the duration as hours, minutes and seconds, e.g. "2:05:00".
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Simulates a session with the options until the given number of Pomodoros is completed.
+///
+/// Asking for the task label is disabled, all other options are used as they are.
+///
+/// # Arguments
+///
+/// * `options` - The Pomodoro options to simulate.
+/// * `speed` - The speed of the simulation.
+/// * `pomodoros` - The number of Pomodoros after which the session ends.
+///
+/// # Returns
+///
+/// The report of the simulation.
+pub fn simulate(options: PomodoroOptions, speed: Speed, pomodoros: i32) -> SimulationReport {
+    let clock = speed.clock();
+    let recorder = EndEventRecorder::new(clock.clone());
+    let mut session = PomodoroSession::new(PomodoroOptions {
+        ask_for_label: false,
+        ..options
+    });
+    session.set_clock(clock.clone());
+    session.record_end_events(recorder.clone());
+    session.set_keep_history(false);
+    session.set_pomodoro_limit(Some(pomodoros));
+
+    let (sender, receiver) = channel();
+    let phase_events = Arc::new(Mutex::new(Vec::new()));
+    let start = clock.now();
+    let recorded_phase_events = phase_events.clone();
+    let phase_clock = clock.clone();
+    session.on_phase_event(move |event| {
+        let at = phase_clock.now().saturating_duration_since(start);
+        recorded_phase_events
+            .lock()
+            .unwrap()
+            .push((at, event.clone()));
+        // Confirms the break or the next Pomodoro. If it starts automatically, the running timer
+        // ignores the input.
+        if let PhaseEvent::Finished { .. } = event {
+            let _ = sender.send("confirm".to_string());
+        }
+    });
+    session.run(&receiver);
+
+    let duration = clock.now().saturating_duration_since(start);
+    let phase_events = phase_events.lock().unwrap().clone();
+    SimulationReport {
+        duration,
+        phase_events,
+        end_events: recorder.events(),
+    }
+}
+
+#[test]
+fn test_parse_speed() {
+    assert_eq!(parse_speed("virtual").unwrap(), Speed::Virtual);
+    assert_eq!(parse_speed("60x").unwrap(), Speed::Factor(60.0));
+    assert_eq!(parse_speed("2.5").unwrap(), Speed::Factor(2.5));
+    assert!(parse_speed("0x").is_err());
+    assert!(parse_speed("fast").is_err());
+}
+
+#[test]
+fn test_simulate_cycle() {
+    use crate::end_events::{EventTime, TimedEvent};
+    use crate::session::Phase;
+
+    let options = PomodoroOptions {
+        duration_pomodoro: 25,
+        additional_duration: 0,
+        duration_short_break: 5,
+        duration_long_break: 15,
+        interval_long_break: 4,
+        auto_start_break: false,
+        auto_start_pomodoro: true,
+        end_event_short_break: Some(EndEvent::None),
+        timed_events: vec![TimedEvent {
+            phase: Phase::Pomodoro,
+            at: EventTime::SecondsBeforeEnd(60),
+            event: EndEvent::LockScreen,
+        }],
+        ..PomodoroOptions::default()
+    };
+    let report = simulate(options, Speed::Virtual, 4);
+
+    // 4 Pomodoros, 3 short breaks and a long break.
+    assert_eq!(
+        report.duration,
+        Duration::from_secs((4 * 25 + 3 * 5 + 15) * 60)
+    );
+    let started: Vec<(u64, Phase)> = report
+        .phase_events
+        .iter()
+        .filter_map(|(at, event)| match event {
+            PhaseEvent::Started { phase, .. } => Some((at.as_secs() / 60, *phase)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        started,
+        vec![
+            (0, Phase::Pomodoro),
+            (25, Phase::ShortBreak),
+            (30, Phase::Pomodoro),
+            (55, Phase::ShortBreak),
+            (60, Phase::Pomodoro),
+            (85, Phase::ShortBreak),
+            (90, Phase::Pomodoro),
+            (115, Phase::LongBreak),
+        ]
+    );
+    assert_eq!(
+        report.phase_events.last().map(|(_, event)| event),
+        Some(&PhaseEvent::SessionEnded { counter: 4 })
+    );
+    let end_events: Vec<(u64, EventTrigger, Phase)> = report
+        .end_events
+        .iter()
+        .map(|recorded| {
+            (
+                recorded.at.as_secs() / 60,
+                recorded.trigger,
+                recorded.context.phase,
+            )
+        })
+        .collect();
+    // The end event of the short breaks is disabled.
+    assert_eq!(
+        &end_events[..4],
+        &[
+            (24, EventTrigger::Timed, Phase::Pomodoro),
+            (25, EventTrigger::End, Phase::Pomodoro),
+            (54, EventTrigger::Timed, Phase::Pomodoro),
+            (55, EventTrigger::End, Phase::Pomodoro),
+        ]
+    );
+    assert_eq!(end_events.len(), 4 * 2 + 1);
+    assert_eq!(end_events[8], (130, EventTrigger::End, Phase::LongBreak));
+    let timeline = report.to_string();
+    assert!(timeline.contains(
+        "  0:25:00  End event of Pomodoro (next: Short break): {\"sound\":{\"filepathSound\":\"\"}}\n  \
+         0:25:00  Short break started (5 min)\n"
+    ));
+    assert!(timeline.ends_with("  2:10:00  Session ended after 4 Pomodoros\n"));
+}
@@ -0,0 +1,536 @@
+//! This module contains the `PomodoroStateMachine` which sequences the phases of a Pomodoro cycle.
+//!
+//! The state machine does not wait, print or play sounds itself. It is driven by `CycleEvent`s,
+//! e.g. a finished phase or a key press of the user, and answers with the `CycleAction`s the
+//! runner has to perform, e.g. starting the next phase. This keeps the sequence
+//! Pomodoro → additional Pomodoro → break → Pomodoro testable without a terminal.
+//!
+//! # Examples
+//!
+//! ```
+//! use locking_pomodoro_timer::pomodoro_options::PomodoroOptions;
+//! use locking_pomodoro_timer::session::Phase;
+//! use locking_pomodoro_timer::state_machine::{CycleAction, CycleEvent, PomodoroStateMachine};
+//!
+//! let options = PomodoroOptions::default();
+//! let mut state_machine = PomodoroStateMachine::new(&options);
+//! let actions = state_machine.handle(CycleEvent::Start);
+//! assert!(matches!(
+//!     actions[0],
+//!     CycleAction::StartPhase { phase: Phase::Pomodoro, .. }
+//! ));
+//! ```
+use crate::pomo_info::PomoInfo;
+use crate::pomodoro_options::PomodoroOptions;
+use crate::session::Phase;
+use log::debug;
+use std::time::Duration;
+
+/// Represents the state of a Pomodoro cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CycleState {
+    /// The cycle has not been started yet.
+    Idle,
+    /// The phase is running.
+    Running(Phase),
+    /// Waiting for the user to confirm the start of the break.
+    AwaitingBreak(Phase),
+    /// Waiting for the user to confirm the start of a new Pomodoro.
+    AwaitingPomodoro,
+    /// The cycle has ended.
+    Ended,
+}
+
+/// Represents the events which drive the state machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CycleEvent {
+    /// Start the cycle with the first Pomodoro.
+    Start,
+    /// The running phase has run for its full duration.
+    PhaseFinished,
+    /// The running phase was quit by the user. The end event of the phase is not started.
+    PhaseQuit,
+    /// The user confirmed to continue, e.g. by pressing enter.
+    Confirm,
+    /// The user declined to continue, e.g. by pressing 'q'.
+    Decline,
+}
+
+/// Represents the actions the runner of the state machine has to perform.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CycleAction {
+    /// Start the phase. The runner reports `PhaseFinished` or `PhaseQuit` when it is over.
+    StartPhase {
+        /// The phase to start.
+        phase: Phase,
+        /// The duration of the phase.
+        duration: Duration,
+        /// The information about the current Pomodoro.
+        pomo_info: PomoInfo,
+    },
+    /// Start the end event of the phase.
+    StartEndEvent {
+        /// The phase which has finished.
+        phase: Phase,
+        /// The phase which follows.
+        next_phase: Phase,
+        /// The information about the Pomodoro of the next phase.
+        pomo_info: PomoInfo,
+    },
+    /// Ask the user to confirm the start of the break. The runner reports `Confirm`.
+    AskForBreak {
+        /// The break to start.
+        phase: Phase,
+        /// The duration of the break.
+        duration: Duration,
+    },
+    /// Ask the user whether to start a new Pomodoro. The runner reports `Confirm` or `Decline`.
+    AskForPomodoro,
+    /// The cycle has ended.
+    End,
+}
+
+/// Represents the state machine of a Pomodoro cycle.
+pub struct PomodoroStateMachine<'a> {
+    options: &'a PomodoroOptions,
+    state: CycleState,
+    counter: i32,
+}
+
+impl<'a> PomodoroStateMachine<'a> {
+    /// Creates a new `PomodoroStateMachine` in the `Idle` state.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The Pomodoro options defining the durations and when to ask the user.
+    pub fn new(options: &'a PomodoroOptions) -> Self {
+        PomodoroStateMachine {
+            options,
+            state: CycleState::Idle,
+            counter: 0,
+        }
+    }
+
+    /// Gets the current state.
+    pub fn state(&self) -> CycleState {
+        self.state
+    }
+
+    /// Gets the number of completed Pomodoros.
+    pub fn counter(&self) -> i32 {
+        self.counter
+    }
+
+    /// Gets the information about the current Pomodoro.
+    pub fn pomo_info(&self) -> PomoInfo {
+        PomoInfo::from_options(self.options, self.counter)
+    }
+
+    /// Handles the event and transitions to the next state.
+    ///
+    /// Events which are not expected in the current state, e.g. `Confirm` while a phase is running,
+    /// are ignored.
+    ///
+    /// # Returns
+    ///
+    /// The actions to perform in the given order.
+    pub fn handle(&mut self, event: CycleEvent) -> Vec<CycleAction> {
+        let actions = match (self.state, event) {
+            (CycleState::Idle, CycleEvent::Start) => vec![self.start_phase(Phase::Pomodoro)],
+            (CycleState::Running(phase), CycleEvent::PhaseFinished) => {
+                let next_actions = self.next_after(phase);
+                let mut actions = vec![CycleAction::StartEndEvent {
+                    phase,
+                    next_phase: upcoming_phase(&next_actions),
+                    pomo_info: self.pomo_info(),
+                }];
+                actions.extend(next_actions);
+                actions
+            }
+            (CycleState::Running(phase), CycleEvent::PhaseQuit) => self.next_after(phase),
+            (CycleState::AwaitingBreak(phase), CycleEvent::Confirm) => {
+                vec![self.start_phase(phase)]
+            }
+            (CycleState::AwaitingPomodoro, CycleEvent::Confirm) => {
+                vec![self.start_phase(Phase::Pomodoro)]
+            }
+            (CycleState::AwaitingPomodoro, CycleEvent::Decline) => {
+                self.state = CycleState::Ended;
+                vec![CycleAction::End]
+            }
+            (state, event) => {
+                debug!("Ignoring event {:?} in state {:?}.", event, state);
+                Vec::new()
+            }
+        };
+        debug!("State after {:?}: {:?}", event, self.state);
+        actions
+    }
+
+    /// Resumes an interrupted cycle with the given phase and counter.
+    ///
+    /// The event is ignored if the cycle has already been started.
+    ///
+    /// # Returns
+    ///
+    /// The action to start the phase.
+    pub fn resume(&mut self, phase: Phase, counter: i32) -> Vec<CycleAction> {
+        if self.state != CycleState::Idle {
+            debug!("Ignoring resume in state {:?}.", self.state);
+            return Vec::new();
+        }
+        self.counter = counter;
+        vec![self.start_phase(phase)]
+    }
+
+    /// Gets the actions to continue the cycle after the phase is over.
+    fn next_after(&mut self, phase: Phase) -> Vec<CycleAction> {
+        match phase {
+            Phase::Pomodoro if self.options.additional_duration != 0 => {
+                vec![self.start_phase(Phase::AdditionalPomodoro)]
+            }
+            Phase::Pomodoro | Phase::AdditionalPomodoro => self.next_after_focus(),
+            Phase::ShortBreak | Phase::LongBreak => self.next_pomodoro(),
+        }
+    }
+
+    /// Gets the actions to start the break after the Pomodoro and the additional Pomodoro.
+    fn next_after_focus(&mut self) -> Vec<CycleAction> {
+        let pomo_info = self.pomo_info();
+        if pomo_info.break_duration.is_zero() {
+            return self.next_pomodoro();
+        }
+        let phase = if pomo_info.is_long_break_coming {
+            Phase::LongBreak
+        } else {
+            Phase::ShortBreak
+        };
+        if self.options.auto_start_break {
+            vec![self.start_phase(phase)]
+        } else {
+            self.state = CycleState::AwaitingBreak(phase);
+            vec![CycleAction::AskForBreak {
+                phase,
+                duration: pomo_info.break_duration,
+            }]
+        }
+    }
+
+    /// Completes the current Pomodoro and gets the actions to start the next one.
+    fn next_pomodoro(&mut self) -> Vec<CycleAction> {
+        self.counter += 1;
+        if self.options.auto_start_pomodoro {
+            vec![self.start_phase(Phase::Pomodoro)]
+        } else {
+            self.state = CycleState::AwaitingPomodoro;
+            vec![CycleAction::AskForPomodoro]
+        }
+    }
+
+    /// Transitions to the running phase and gets the action to start it.
+    fn start_phase(&mut self, phase: Phase) -> CycleAction {
+        self.state = CycleState::Running(phase);
+        let pomo_info = self.pomo_info();
+        let duration = match phase {
+            Phase::Pomodoro => Duration::from_secs((self.options.duration_pomodoro * 60) as u64),
+            Phase::AdditionalPomodoro => {
+                Duration::from_secs((self.options.additional_duration * 60) as u64)
+            }
+            Phase::ShortBreak | Phase::LongBreak => pomo_info.break_duration,
+        };
+        CycleAction::StartPhase {
+            phase,
+            duration,
+            pomo_info,
+        }
+    }
+}
+
+/// Gets the phase which is started or asked for by the actions.
+fn upcoming_phase(actions: &[CycleAction]) -> Phase {
+    actions
+        .iter()
+        .find_map(|action| match action {
+            CycleAction::StartPhase { phase, .. } | CycleAction::AskForBreak { phase, .. } => {
+                Some(*phase)
+            }
+            _ => None,
+        })
+        .unwrap_or(Phase::Pomodoro)
+}
+
+/// Runs the state machine like a user who finishes every phase and always confirms.
+///
+/// # Returns
+///
+/// A trace of the actions until `pomodoros` Pomodoros were started.
+#[cfg(test)]
+fn trace_cycle(options: &PomodoroOptions, pomodoros: usize) -> Vec<String> {
+    let mut state_machine = PomodoroStateMachine::new(options);
+    let mut trace = Vec::new();
+    let mut actions = state_machine.handle(CycleEvent::Start);
+    let mut started_pomodoros = 0;
+    loop {
+        let mut event = None;
+        for action in actions {
+            match action {
+                CycleAction::StartPhase { phase, .. } => {
+                    if phase == Phase::Pomodoro {
+                        started_pomodoros += 1;
+                        if started_pomodoros > pomodoros {
+                            return trace;
+                        }
+                    }
+                    trace.push(format!("start {:?}", phase));
+                    event = Some(CycleEvent::PhaseFinished);
+                }
+                CycleAction::StartEndEvent { phase, .. } => {
+                    trace.push(format!("end event {:?}", phase))
+                }
+                CycleAction::AskForBreak { phase, .. } => {
+                    trace.push(format!("ask {:?}", phase));
+                    event = Some(CycleEvent::Confirm);
+                }
+                CycleAction::AskForPomodoro => {
+                    trace.push("ask Pomodoro".to_string());
+                    event = Some(CycleEvent::Confirm);
+                }
+                CycleAction::End => return trace,
+            }
+        }
+        actions = state_machine.handle(event.expect("The state machine got stuck."));
+    }
+}
+
+#[test]
+fn test_transitions_for_all_option_combinations() {
+    for auto_start_break in [true, false] {
+        for auto_start_pomodoro in [true, false] {
+            for zero_breaks in [true, false] {
+                for zero_additional_duration in [true, false] {
+                    let options = PomodoroOptions {
+                        auto_start_break,
+                        auto_start_pomodoro,
+                        interval_long_break: 2,
+                        duration_short_break: if zero_breaks { 0 } else { 5 },
+                        duration_long_break: if zero_breaks { 0 } else { 15 },
+                        additional_duration: if zero_additional_duration { 0 } else { 5 },
+                        ..Default::default()
+                    };
+                    let mut expected = Vec::new();
+                    for (pomodoro, break_phase) in
+                        [Phase::ShortBreak, Phase::LongBreak, Phase::ShortBreak]
+                            .into_iter()
+                            .enumerate()
+                    {
+                        if pomodoro != 0 && !auto_start_pomodoro {
+                            expected.push("ask Pomodoro".to_string());
+                        }
+                        expected.push("start Pomodoro".to_string());
+                        expected.push("end event Pomodoro".to_string());
+                        if !zero_additional_duration {
+                            expected.push("start AdditionalPomodoro".to_string());
+                            expected.push("end event AdditionalPomodoro".to_string());
+                        }
+                        if !zero_breaks {
+                            if !auto_start_break {
+                                expected.push(format!("ask {:?}", break_phase));
+                            }
+                            expected.push(format!("start {:?}", break_phase));
+                            expected.push(format!("end event {:?}", break_phase));
+                        }
+                    }
+                    if !auto_start_pomodoro {
+                        expected.push("ask Pomodoro".to_string());
+                    }
+
+                    assert_eq!(
+                        trace_cycle(&options, 3),
+                        expected,
+                        "auto_start_break: {}, auto_start_pomodoro: {}, zero_breaks: {}, zero_additional_duration: {}",
+                        auto_start_break,
+                        auto_start_pomodoro,
+                        zero_breaks,
+                        zero_additional_duration
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_zero_short_break_with_long_break() {
+    let options = PomodoroOptions {
+        interval_long_break: 2,
+        duration_short_break: 0,
+        additional_duration: 0,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        trace_cycle(&options, 2),
+        vec![
+            "start Pomodoro",
+            "end event Pomodoro",
+            "start Pomodoro",
+            "end event Pomodoro",
+            "start LongBreak",
+            "end event LongBreak",
+        ]
+    );
+}
+
+#[test]
+fn test_quit_phase_skips_end_event_and_continues() {
+    let options = PomodoroOptions::default();
+    let mut state_machine = PomodoroStateMachine::new(&options);
+    state_machine.handle(CycleEvent::Start);
+
+    let actions = state_machine.handle(CycleEvent::PhaseQuit);
+
+    assert_eq!(actions.len(), 1);
+    assert!(matches!(
+        actions[0],
+        CycleAction::StartPhase {
+            phase: Phase::AdditionalPomodoro,
+            ..
+        }
+    ));
+    assert_eq!(
+        state_machine.state(),
+        CycleState::Running(Phase::AdditionalPomodoro)
+    );
+}
+
+#[test]
+fn test_decline_new_pomodoro_ends_cycle() {
+    let options = PomodoroOptions {
+        auto_start_pomodoro: false,
+        additional_duration: 0,
+        duration_short_break: 0,
+        ..Default::default()
+    };
+    let mut state_machine = PomodoroStateMachine::new(&options);
+    state_machine.handle(CycleEvent::Start);
+    state_machine.handle(CycleEvent::PhaseFinished);
+    assert_eq!(state_machine.state(), CycleState::AwaitingPomodoro);
+
+    assert_eq!(
+        state_machine.handle(CycleEvent::Decline),
+        vec![CycleAction::End]
+    );
+    assert_eq!(state_machine.state(), CycleState::Ended);
+    assert_eq!(state_machine.counter(), 1);
+}
+
+#[test]
+fn test_unexpected_events_are_ignored() {
+    let options = PomodoroOptions {
+        auto_start_break: false,
+        additional_duration: 0,
+        ..Default::default()
+    };
+    let mut state_machine = PomodoroStateMachine::new(&options);
+    assert!(state_machine.handle(CycleEvent::PhaseFinished).is_empty());
+    state_machine.handle(CycleEvent::Start);
+    assert!(state_machine.handle(CycleEvent::Confirm).is_empty());
+    state_machine.handle(CycleEvent::PhaseFinished);
+    assert_eq!(
+        state_machine.state(),
+        CycleState::AwaitingBreak(Phase::ShortBreak)
+    );
+
+    // Only enter starts the break.
+    assert!(state_machine.handle(CycleEvent::Decline).is_empty());
+    assert_eq!(
+        state_machine.state(),
+        CycleState::AwaitingBreak(Phase::ShortBreak)
+    );
+}
+
+#[test]
+fn test_pomo_info_is_derived_from_counter() {
+    let options = PomodoroOptions {
+        interval_long_break: 2,
+        additional_duration: 0,
+        ..Default::default()
+    };
+    let mut state_machine = PomodoroStateMachine::new(&options);
+    state_machine.handle(CycleEvent::Start);
+    assert_eq!(state_machine.pomo_info().pomodoros_till_long_break, 2);
+
+    let actions = state_machine.handle(CycleEvent::PhaseFinished);
+    assert_eq!(
+        actions[1],
+        CycleAction::StartPhase {
+            phase: Phase::ShortBreak,
+            duration: Duration::from_secs(5 * 60),
+            pomo_info: PomoInfo::from_options(&options, 0),
+        }
+    );
+    state_machine.handle(CycleEvent::PhaseFinished);
+    assert_eq!(state_machine.counter(), 1);
+    assert!(state_machine.pomo_info().is_long_break_coming);
+    assert_eq!(state_machine.pomo_info().pomodoros_till_long_break, 1);
+}
+
+#[test]
+fn test_end_event_knows_next_phase() {
+    let options = PomodoroOptions {
+        interval_long_break: 2,
+        auto_start_pomodoro: false,
+        ..Default::default()
+    };
+    let mut state_machine = PomodoroStateMachine::new(&options);
+    state_machine.handle(CycleEvent::Start);
+
+    let actions = state_machine.handle(CycleEvent::PhaseFinished);
+    assert!(matches!(
+        actions[0],
+        CycleAction::StartEndEvent {
+            phase: Phase::Pomodoro,
+            next_phase: Phase::AdditionalPomodoro,
+            ..
+        }
+    ));
+    state_machine.handle(CycleEvent::PhaseFinished);
+    let actions = state_machine.handle(CycleEvent::PhaseFinished);
+    assert_eq!(
+        actions[0],
+        CycleAction::StartEndEvent {
+            phase: Phase::ShortBreak,
+            next_phase: Phase::Pomodoro,
+            pomo_info: PomoInfo::from_options(&options, 1),
+        }
+    );
+}
+
+#[test]
+fn test_resume_continues_cycle() {
+    let options = PomodoroOptions {
+        interval_long_break: 4,
+        additional_duration: 0,
+        ..Default::default()
+    };
+    let mut state_machine = PomodoroStateMachine::new(&options);
+
+    let actions = state_machine.resume(Phase::Pomodoro, 3);
+    assert_eq!(
+        actions,
+        vec![CycleAction::StartPhase {
+            phase: Phase::Pomodoro,
+            duration: Duration::from_secs(25 * 60),
+            pomo_info: PomoInfo::from_options(&options, 3),
+        }]
+    );
+    assert!(state_machine.resume(Phase::Pomodoro, 0).is_empty());
+    let actions = state_machine.handle(CycleEvent::PhaseFinished);
+    assert!(matches!(
+        actions[1],
+        CycleAction::StartPhase {
+            phase: Phase::LongBreak,
+            ..
+        }
+    ));
+}
@@ -0,0 +1,386 @@
+//! This module creates productivity reports from the session history.
+//!
+//! The records of the history are grouped by day, week or tag and summarized into `StatsRow`s,
+//! which can be printed as a table, JSON or CSV.
+use crate::history::HistoryRecord;
+use crate::session::Phase;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Represents how the records are grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// Group by the day the phase started.
+    Day,
+    /// Group by the ISO week the phase started.
+    Week,
+    /// Group by the label of the task.
+    #[value(alias = "label")]
+    Tag,
+}
+
+/// Represents the output format of the statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StatsFormat {
+    /// A table for the terminal.
+    Table,
+    /// A JSON object with the rows and the total.
+    Json,
+    /// Comma-separated values with a header line.
+    Csv,
+}
+
+/// Represents the statistics of a group of records.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsRow {
+    /// The name of the group, e.g. the day `2024-05-22` or the week `2024-W21`.
+    pub group: String,
+    /// The number of Pomodoros which were not quit.
+    pub completed_pomodoros: u32,
+    /// The minutes spent in Pomodoros and additional Pomodoros.
+    pub focus_minutes: f64,
+    /// The minutes spent in short and long breaks.
+    pub break_minutes: f64,
+    /// The ratio of finished Pomodoros to all started Pomodoros between 0 and 1.
+    pub completion_rate: f64,
+    /// The average time a phase was paused in seconds.
+    pub average_pause_seconds: f64,
+}
+
+/// Represents the statistics of the reported period.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    /// The statistics of every group sorted by the name of the group.
+    pub rows: Vec<StatsRow>,
+    /// The statistics of all records in the period.
+    pub total: StatsRow,
+}
+
+/// Parses the start of the reported period.
+///
+/// The period can be given relative to `now` like `12h`, `7d` or `2w`, as `today` or as a date
+/// like `2024-05-22`.
+///
+/// # Errors
+///
+/// Returns an error if the period could not be parsed.
+pub fn parse_since(since: &str, now: DateTime<Local>) -> Result<DateTime<Local>> {
+    let since = since.trim();
+    if since == "today" {
+        return start_of_day(now.date_naive());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+        return start_of_day(date);
+    }
+    let invalid_period = || {
+        format!(
+            "Invalid period: {:?}. Use e.g. 12h, 7d, 2w, today or 2024-05-22.",
+            since
+        )
+    };
+    // The unit is the last character, which is not necessarily a single byte.
+    let (amount, unit) = match since.char_indices().last() {
+        Some((index, _)) => since.split_at(index),
+        None => bail!(invalid_period()),
+    };
+    let amount: i64 = amount.parse().with_context(invalid_period)?;
+    if amount < 0 {
+        bail!(
+            "Invalid period: {:?}. The period must not be negative.",
+            since
+        );
+    }
+    let duration = match unit {
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => bail!("Invalid unit of period: {:?}. Use h, d or w.", since),
+    };
+    duration
+        .and_then(|duration| now.checked_sub_signed(duration))
+        .with_context(|| format!("Invalid period: {:?}. The period is too long.", since))
+}
+
+/// Gets the local midnight at the start of the date.
+fn start_of_day(date: NaiveDate) -> Result<DateTime<Local>> {
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+        .earliest()
+        .with_context(|| format!("Invalid local date: {}", date))
+}
+
+/// Creates the statistics of the records which started at or after `since`.
+///
+/// # Arguments
+///
+/// * `records` - The records of the history.
+/// * `since` - The start of the reported period.
+/// * `group_by` - How the records are grouped.
+///
+/// # Returns
+///
+/// The statistics of every group and of all records in the period.
+pub fn create_stats(records: &[HistoryRecord], since: DateTime<Local>, group_by: GroupBy) -> Stats {
+    let records: Vec<&HistoryRecord> = records
+        .iter()
+        .filter(|record| record.start >= since)
+        .collect();
+    let mut groups: BTreeMap<String, Vec<&HistoryRecord>> = BTreeMap::new();
+    for record in &records {
+        let group = match group_by {
+            GroupBy::Day => record.start.format("%Y-%m-%d").to_string(),
+            GroupBy::Week => record.start.format("%G-W%V").to_string(),
+            GroupBy::Tag => record.label.clone().unwrap_or_else(|| "(none)".to_string()),
+        };
+        groups.entry(group).or_default().push(record);
+    }
+    Stats {
+        rows: groups
+            .into_iter()
+            .map(|(group, records)| summarize(group, &records))
+            .collect(),
+        total: summarize("Total".to_string(), &records),
+    }
+}
+
+/// Summarizes the records of a group.
+fn summarize(group: String, records: &[&HistoryRecord]) -> StatsRow {
+    let pomodoros = records
+        .iter()
+        .filter(|record| record.phase == Phase::Pomodoro);
+    let started_pomodoros = pomodoros.clone().count() as u32;
+    let completed_pomodoros = pomodoros.filter(|record| !record.quit).count() as u32;
+    let minutes_of = |phases: &[Phase]| {
+        records
+            .iter()
+            .filter(|record| phases.contains(&record.phase))
+            .map(|record| record.actual_seconds as f64 / 60.0)
+            .fold(0.0, |sum, minutes| sum + minutes)
+    };
+    let paused_seconds: u64 = records.iter().map(|record| record.paused_seconds).sum();
+    StatsRow {
+        group,
+        completed_pomodoros,
+        focus_minutes: minutes_of(&[Phase::Pomodoro, Phase::AdditionalPomodoro]),
+        break_minutes: minutes_of(&[Phase::ShortBreak, Phase::LongBreak]),
+        completion_rate: if started_pomodoros == 0 {
+            0.0
+        } else {
+            completed_pomodoros as f64 / started_pomodoros as f64
+        },
+        average_pause_seconds: if records.is_empty() {
+            0.0
+        } else {
+            paused_seconds as f64 / records.len() as f64
+        },
+    }
+}
+
+/// Formats the statistics in the given format.
+///
+/// The table contains the total in the last line. The CSV format only contains the groups.
+///
+/// # Errors
+///
+/// Returns an error if the statistics could not be serialized.
+pub fn format_stats(stats: &Stats, format: StatsFormat) -> Result<String> {
+    match format {
+        StatsFormat::Table => Ok(format_table(stats)),
+        StatsFormat::Json => {
+            serde_json::to_string_pretty(stats).context("Failed to serialize statistics.")
+        }
+        StatsFormat::Csv => Ok(format_csv(&stats.rows)),
+    }
+}
+
+/// Formats the statistics as a table with the total in the last line.
+fn format_table(stats: &Stats) -> String {
+    if stats.rows.is_empty() {
+        return "No phases recorded in this period.".to_string();
+    }
+    let header = [
+        "Group",
+        "Pomodoros",
+        "Focus (min)",
+        "Break (min)",
+        "Completion",
+        "Avg. pause",
+    ];
+    let cells: Vec<[String; 6]> = stats
+        .rows
+        .iter()
+        .chain(std::iter::once(&stats.total))
+        .map(|row| {
+            [
+                row.group.clone(),
+                row.completed_pomodoros.to_string(),
+                format!("{:.0}", row.focus_minutes),
+                format!("{:.0}", row.break_minutes),
+                format!("{:.0} %", row.completion_rate * 100.0),
+                format!(
+                    "{}:{:02}",
+                    row.average_pause_seconds as u64 / 60,
+                    row.average_pause_seconds as u64 % 60
+                ),
+            ]
+        })
+        .collect();
+    let mut widths = header.map(str::len);
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let format_line = |line: &[String]| {
+        line.iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(column, (cell, width))| {
+                // The group is aligned left, the numbers are aligned right.
+                if column == 0 {
+                    format!("{:<width$}", cell, width = width)
+                } else {
+                    format!("{:>width$}", cell, width = width)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    };
+    let separator = widths
+        .iter()
+        .map(|width| "-".repeat(*width))
+        .collect::<Vec<_>>()
+        .join("-+-");
+    let mut lines = vec![format_line(&header.map(String::from)), separator.clone()];
+    let (total_line, group_lines) = cells.split_last().unwrap();
+    lines.extend(group_lines.iter().map(|row| format_line(row)));
+    lines.push(separator);
+    lines.push(format_line(total_line));
+    lines.join("\n")
+}
+
+/// Formats the rows as comma-separated values with a header line.
+fn format_csv(rows: &[StatsRow]) -> String {
+    let mut lines = vec![
+        "group,completedPomodoros,focusMinutes,breakMinutes,completionRate,averagePauseSeconds"
+            .to_string(),
+    ];
+    for row in rows {
+        lines.push(format!(
+            "{},{},{:.2},{:.2},{:.4},{:.1}",
+            escape_csv_field(&row.group),
+            row.completed_pomodoros,
+            row.focus_minutes,
+            row.break_minutes,
+            row.completion_rate,
+            row.average_pause_seconds
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Quotes the field if it contains a comma, a quote or a line break.
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+fn create_record(start: DateTime<Local>, phase: Phase, minutes: u64, quit: bool) -> HistoryRecord {
+    HistoryRecord {
+        start,
+        end: start + Duration::minutes(minutes as i64),
+        phase,
+        planned_seconds: 25 * 60,
+        actual_seconds: minutes * 60,
+        paused_seconds: 30,
+        skipped_seconds: 0,
+        quit,
+        label: None,
+    }
+}
+
+#[test]
+fn test_create_stats() {
+    // The records are grouped by local time, so they are created in local time.
+    let local = |year, month, day, hour, minute| {
+        Local
+            .with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    };
+    let mut records = vec![
+        create_record(local(2024, 5, 20, 9, 0), Phase::Pomodoro, 25, false),
+        create_record(local(2024, 5, 20, 9, 25), Phase::ShortBreak, 5, false),
+        create_record(local(2024, 5, 20, 9, 30), Phase::Pomodoro, 10, true),
+        create_record(local(2024, 5, 27, 9, 0), Phase::Pomodoro, 25, false),
+        create_record(
+            local(2024, 5, 27, 9, 25),
+            Phase::AdditionalPomodoro,
+            5,
+            false,
+        ),
+        // Before the reported period.
+        create_record(local(2024, 5, 1, 9, 0), Phase::Pomodoro, 25, false),
+    ];
+    records[3].label = Some("coding".to_string());
+    let since = parse_since("2024-05-10", Local::now()).unwrap();
+
+    let stats = create_stats(&records, since, GroupBy::Week);
+    let rows = &stats.rows;
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].group, "2024-W21");
+    assert_eq!(rows[0].completed_pomodoros, 1);
+    assert_eq!(rows[0].focus_minutes, 35.0);
+    assert_eq!(rows[0].break_minutes, 5.0);
+    assert_eq!(rows[0].completion_rate, 0.5);
+    assert_eq!(rows[0].average_pause_seconds, 30.0);
+    assert_eq!(rows[1].group, "2024-W22");
+    assert_eq!(rows[1].focus_minutes, 30.0);
+    assert_eq!(rows[1].completion_rate, 1.0);
+    assert_eq!(stats.total.completed_pomodoros, 2);
+    assert_eq!(stats.total.completion_rate, 2.0 / 3.0);
+
+    let stats = create_stats(&records, since, GroupBy::Tag);
+    assert_eq!(
+        stats
+            .rows
+            .iter()
+            .map(|row| row.group.as_str())
+            .collect::<Vec<_>>(),
+        vec!["(none)", "coding"]
+    );
+    assert_eq!(
+        format_stats(&stats, StatsFormat::Csv)
+            .unwrap()
+            .lines()
+            .nth(2),
+        Some("coding,1,25.00,0.00,1.0000,30.0")
+    );
+}
+
+#[test]
+fn test_parse_since() {
+    let now = Local::now();
+
+    assert_eq!(parse_since("7d", now).unwrap(), now - Duration::days(7));
+    assert_eq!(parse_since("2w", now).unwrap(), now - Duration::weeks(2));
+    assert_eq!(parse_since("12h", now).unwrap(), now - Duration::hours(12));
+    assert_eq!(
+        parse_since("today", now).unwrap().date_naive(),
+        now.date_naive()
+    );
+    assert!(parse_since("7x", now).is_err());
+    assert!(parse_since("", now).is_err());
+    assert!(parse_since("7ä", now).is_err());
+    assert!(parse_since("ä", now).is_err());
+    assert!(parse_since("-7d", now).is_err());
+    assert!(parse_since("9223372036854775807w", now).is_err());
+}
@@ -1,21 +1,60 @@
+//! This module contains the `Timer` which measures the elapsed time of a phase.
+use crate::clock::{Clock, SystemClock};
 use log::trace;
 use std::{
-    sync::{
-        atomic::{AtomicBool, AtomicU32, Ordering},
-        Arc,
-    },
-    thread,
-    time::Duration,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
-/// Represents a timer that counts the elapsed time.
+/// Represents a timer that measures the elapsed time.
 ///
-/// The timer runs in a separate thread and counts the elapsed time in seconds.
-pub(crate) struct Timer {
+/// The timer does not count in a separate thread. It stores the instants of its clock when it
+/// was started, paused and resumed and computes the elapsed time from them, so it neither drifts
+/// nor depends on how often it is queried. By default, the monotonic clock of the system is used.
+pub struct Timer {
     duration: Duration,
-    elapsed_time: Arc<AtomicU32>,
-    paused: Arc<AtomicBool>,
-    should_terminate: Arc<AtomicBool>,
+    clock: Arc<dyn Clock>,
+    state: Mutex<TimerState>,
+}
+
+/// The checkpoints from which the elapsed time of a `Timer` is computed.
+#[derive(Debug, Default)]
+struct TimerState {
+    /// Whether the timer has been started.
+    started: bool,
+    /// Whether the timer has been stopped. A stopped timer can not be resumed.
+    stopped: bool,
+    /// Whether the timer is paused.
+    paused: bool,
+    /// The elapsed time up to the last checkpoint, including skipped time.
+    elapsed_before: Duration,
+    /// The time since when the timer is running, if it is running.
+    running_since: Option<Instant>,
+    /// The time when the current pause started.
+    paused_since: Option<Instant>,
+    /// The time of all finished pauses.
+    paused_time: Duration,
+    /// The time skipped using the `skip` method.
+    skipped_time: Duration,
+    /// The time added to the duration using the `extend` method.
+    extended_time: Duration,
+}
+
+impl TimerState {
+    /// Adds the time since the timer was running to the elapsed time, so the timer is halted.
+    fn halt(&mut self, now: Instant) {
+        if let Some(running_since) = self.running_since.take() {
+            self.elapsed_before += now.saturating_duration_since(running_since);
+        }
+    }
+
+    /// Computes the elapsed time at the given instant.
+    fn elapsed_time(&self, now: Instant) -> Duration {
+        self.elapsed_before
+            + self.running_since.map_or(Duration::ZERO, |running_since| {
+                now.saturating_duration_since(running_since)
+            })
+    }
 }
 
 impl Timer {
@@ -25,95 +64,196 @@ impl Timer {
     ///
     /// * `duration` - The duration after which the timer should stop.
     pub fn new(duration: Duration) -> Self {
+        Timer::with_clock(duration, Arc::new(SystemClock))
+    }
+
+    /// Creates a new Timer instance which measures the time with the given clock.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - The duration after which the timer should stop.
+    /// * `clock` - The clock measuring the time, e.g. a `VirtualClock` for a simulation.
+    pub fn with_clock(duration: Duration, clock: Arc<dyn Clock>) -> Self {
         Timer {
             duration,
-            elapsed_time: Arc::new(AtomicU32::new(0)),
-            paused: Arc::new(AtomicBool::new(false)),
-            should_terminate: Arc::new(AtomicBool::new(false)),
+            clock,
+            state: Mutex::new(TimerState::default()),
         }
     }
 
-    /// Starts the timer in a separate thread.
+    /// Lets the timer continue after the given elapsed time, e.g. to resume an interrupted phase.
+    ///
+    /// # Arguments
     ///
-    /// The thread sleeps for 0.5 seconds and increments the elapsed time when more than 1 second has passed.
-    /// The timer stops when the elapsed time reaches the specified duration.
+    /// * `elapsed_time` - The time which has already elapsed.
+    pub fn with_elapsed_time(self, elapsed_time: Duration) -> Self {
+        self.state.lock().unwrap().elapsed_before = elapsed_time.min(self.duration);
+        self
+    }
+
+    /// Gets the clock measuring the time of the timer.
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Starts the timer.
+    ///
+    /// The time elapses from now on unless the timer is paused.
     /// The timer can be paused and resumed using the `pause` and `resume` methods.
     /// The timer can be stopped using the `stop` method.
-    /// The thread terminates when the timer stops.
     pub fn start(&self) {
-        let elapsed_time_storage = self.elapsed_time.clone();
-        let duration = self.duration;
-        let mut time_buffer = 0.0;
-        let paused = self.paused.clone();
-        let should_terminate = self.should_terminate.clone();
-        thread::spawn(move || {
-            let delta = 0.5;
-            while elapsed_time_storage.load(Ordering::Relaxed) < duration.as_secs() as u32 {
-                if should_terminate.load(Ordering::Relaxed) {
-                    break;
-                }
-                thread::sleep(Duration::from_secs_f64(delta));
-                if paused.load(Ordering::Relaxed) {
-                    continue;
-                }
-                time_buffer += delta;
-                if time_buffer >= 1.0 {
-                    let time_to_add = time_buffer as u32;
-                    time_buffer -= time_to_add as f64;
-                    elapsed_time_storage.fetch_add(time_to_add, Ordering::Relaxed);
-                }
-            }
-            trace!("Timer thread terminated.");
-        });
+        let mut state = self.state.lock().unwrap();
+        if state.started {
+            return;
+        }
+        state.started = true;
+        if !state.paused {
+            state.running_since = Some(self.clock.now());
+        }
     }
 
     /// Pauses the timer.
     pub fn pause(&self) {
-        self.paused.store(true, Ordering::Relaxed);
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap();
+        if state.paused || state.stopped {
+            return;
+        }
+        state.halt(now);
+        state.paused = true;
+        state.paused_since = Some(now);
     }
 
     /// Resumes the timer.
     pub fn resume(&self) {
-        self.paused.store(false, Ordering::Relaxed);
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap();
+        if !state.paused {
+            return;
+        }
+        state.paused = false;
+        if let Some(paused_since) = state.paused_since.take() {
+            state.paused_time += now.saturating_duration_since(paused_since);
+        }
+        if state.started && !state.stopped {
+            state.running_since = Some(now);
+        }
     }
 
     /// Skip specified duration.
+    ///
+    /// The timer does not skip beyond its duration.
     pub fn skip(&self, duration: Duration) {
-        log::trace!("Skip duration: {:?}", duration);
-        let elapsed_time = self.elapsed_time.load(Ordering::Relaxed);
-        let new_elapsed_time = elapsed_time + duration.as_secs() as u32;
-        if new_elapsed_time >= self.duration.as_secs() as u32 {
-            self.elapsed_time
-                .store(self.duration.as_secs() as u32, Ordering::Relaxed);
-            return;
-        } else {
-            self.elapsed_time.store(new_elapsed_time, Ordering::Relaxed);
-        }
-        log::trace!("Elapsed time after skip: {:?}", self.elapsed_time.load(Ordering::Relaxed));
+        trace!("Skip duration: {:?}", duration);
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap();
+        let total_duration = self.duration + state.extended_time;
+        let remaining_time = total_duration.saturating_sub(state.elapsed_time(now));
+        let skipped_time = duration.min(remaining_time);
+        state.elapsed_before += skipped_time;
+        state.skipped_time += skipped_time;
+        trace!("Elapsed time after skip: {:?}", state.elapsed_time(now));
+    }
+
+    /// Extends the duration of the timer, e.g. to finish the current thought.
+    pub fn extend(&self, duration: Duration) {
+        trace!("Extend duration: {:?}", duration);
+        self.state.lock().unwrap().extended_time += duration;
     }
 
     /// Checks if the timer is currently paused.
     ///
     /// Returns `true` if the timer is paused, `false` otherwise.
     pub fn is_paused(&self) -> bool {
-        self.paused.load(Ordering::Relaxed)
+        self.state.lock().unwrap().paused
     }
 
-    /// Stops the timer. The timer thread will terminate.
+    /// Stops the timer. The elapsed time does not change anymore.
     pub fn stop(&self) {
-        self.should_terminate.store(true, Ordering::Relaxed);
+        let mut state = self.state.lock().unwrap();
+        state.halt(self.clock.now());
+        state.stopped = true;
+    }
+
+    /// Gets the time the timer was paused, including the current pause.
+    pub fn get_paused_time(&self) -> Duration {
+        let state = self.state.lock().unwrap();
+        let current_pause = state.paused_since.map_or(Duration::ZERO, |paused_since| {
+            self.clock.now().saturating_duration_since(paused_since)
+        });
+        state.paused_time + current_pause
     }
 
-    /// Gets the elapsed time of the timer.
+    /// Gets the time skipped using the `skip` method.
+    pub fn get_skipped_time(&self) -> Duration {
+        self.state.lock().unwrap().skipped_time
+    }
+
+    /// Gets the duration after which the timer stops, including the extensions.
+    pub fn get_duration(&self) -> Duration {
+        self.duration + self.state.lock().unwrap().extended_time
+    }
+
+    /// Gets the elapsed time of the timer with sub-second precision.
+    ///
+    /// The elapsed time excludes pauses, includes skipped time and never exceeds the duration.
     pub fn get_elapsed_time(&self) -> Duration {
-        let elapsed_time = self.elapsed_time.load(Ordering::Relaxed);
-        Duration::from_secs(elapsed_time as u64)
+        let state = self.state.lock().unwrap();
+        let elapsed_time = state.elapsed_time(self.clock.now());
+        elapsed_time.min(self.duration + state.extended_time)
     }
-}
 
-impl Drop for Timer {
-    fn drop(&mut self) {
-        self.stop();
-        trace!("Timer dropped.");
+    /// Gets the remaining time of the timer with sub-second precision.
+    pub fn get_remaining_time(&self) -> Duration {
+        self.get_duration().saturating_sub(self.get_elapsed_time())
     }
 }
+
+#[test]
+fn test_timer_computes_elapsed_time() {
+    use std::thread::sleep;
+
+    let timer = Timer::new(Duration::from_secs(60));
+    sleep(Duration::from_millis(20));
+    assert_eq!(timer.get_elapsed_time(), Duration::ZERO);
+
+    timer.start();
+    sleep(Duration::from_millis(50));
+    let elapsed_time = timer.get_elapsed_time();
+    assert!(elapsed_time >= Duration::from_millis(50));
+    assert!(elapsed_time < Duration::from_secs(1));
+
+    timer.pause();
+    let paused_elapsed_time = timer.get_elapsed_time();
+    sleep(Duration::from_millis(50));
+    assert_eq!(timer.get_elapsed_time(), paused_elapsed_time);
+    assert!(timer.get_paused_time() >= Duration::from_millis(50));
+
+    timer.skip(Duration::from_secs(30));
+    assert_eq!(
+        timer.get_elapsed_time(),
+        paused_elapsed_time + Duration::from_secs(30)
+    );
+    timer.skip(Duration::from_secs(60));
+    assert_eq!(timer.get_elapsed_time(), Duration::from_secs(60));
+    assert_eq!(
+        timer.get_skipped_time(),
+        Duration::from_secs(60) - paused_elapsed_time
+    );
+    assert_eq!(timer.get_remaining_time(), Duration::ZERO);
+    timer.extend(Duration::from_secs(5 * 60));
+    assert_eq!(timer.get_duration(), Duration::from_secs(6 * 60));
+    assert_eq!(timer.get_remaining_time(), Duration::from_secs(5 * 60));
+
+    // A timer paused before it starts does not run until it is resumed.
+    let timer = Timer::new(Duration::from_secs(60)).with_elapsed_time(Duration::from_secs(20));
+    timer.pause();
+    timer.start();
+    sleep(Duration::from_millis(20));
+    assert_eq!(timer.get_elapsed_time(), Duration::from_secs(20));
+    timer.resume();
+    timer.stop();
+    let stopped_elapsed_time = timer.get_elapsed_time();
+    sleep(Duration::from_millis(20));
+    assert_eq!(timer.get_elapsed_time(), stopped_elapsed_time);
+}
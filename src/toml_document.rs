@@ -0,0 +1,186 @@
+//! Translation between TOML documents and the JSON values the options are modelled with.
+//!
+//! The options file is read into a `serde_json::Value` before it is deserialized, so the profiles
+//! can be merged the same way for every format. TOML has no null value and a table in an array
+//! can only be written as array of tables if all items of the array are tables. Other arrays,
+//! e.g. a sequence of end events like `[{ sound = { builtin = "bell" } }, "lockScreen"]`, are
+//! written inline.
+use anyhow::{anyhow, bail, Result};
+use serde_json::{Map, Number, Value};
+use toml_edit::{Array, ArrayOfTables, DocumentMut, InlineTable, Item, Table};
+
+/// Parses a TOML document into a JSON value. Dates and times are read as strings.
+///
+/// # Errors
+///
+/// Returns an error if the contents are not valid TOML or contain a float which JSON can not
+/// represent, e.g. `nan`.
+pub(crate) fn parse_toml(contents: &str) -> Result<Value> {
+    let document: DocumentMut = contents.parse()?;
+    table_to_json(document.as_table())
+}
+
+/// Formats a JSON object as TOML document. Fields set to `null` are left out.
+///
+/// # Errors
+///
+/// Returns an error if the value is not an object, an array contains `null` or a number is too
+/// large for TOML.
+pub(crate) fn format_toml(value: &Value) -> Result<String> {
+    let Value::Object(fields) = value else {
+        bail!("Only an object can be written as TOML document.");
+    };
+    let mut document = DocumentMut::new();
+    *document.as_table_mut() = json_to_table(fields)?;
+    document.as_table_mut().set_implicit(false);
+    Ok(document.to_string())
+}
+
+/// Converts a table of a TOML document into a JSON object.
+fn table_to_json(table: &Table) -> Result<Value> {
+    let mut fields = Map::new();
+    for (key, item) in table.iter() {
+        let value = match item {
+            Item::None => continue,
+            Item::Value(value) => value_to_json(value)?,
+            Item::Table(table) => table_to_json(table)?,
+            Item::ArrayOfTables(tables) => {
+                Value::Array(tables.iter().map(table_to_json).collect::<Result<_>>()?)
+            }
+        };
+        fields.insert(key.to_string(), value);
+    }
+    Ok(Value::Object(fields))
+}
+
+/// Converts a value of a TOML document into a JSON value.
+fn value_to_json(value: &toml_edit::Value) -> Result<Value> {
+    let value = match value {
+        toml_edit::Value::String(string) => Value::String(string.value().clone()),
+        toml_edit::Value::Integer(integer) => Value::from(*integer.value()),
+        toml_edit::Value::Float(float) => Number::from_f64(*float.value())
+            .map(Value::Number)
+            .ok_or_else(|| anyhow!("The float {} can not be read.", float.value()))?,
+        toml_edit::Value::Boolean(boolean) => Value::Bool(*boolean.value()),
+        toml_edit::Value::Datetime(datetime) => Value::String(datetime.value().to_string()),
+        toml_edit::Value::Array(items) => {
+            Value::Array(items.iter().map(value_to_json).collect::<Result<_>>()?)
+        }
+        toml_edit::Value::InlineTable(table) => {
+            let mut fields = Map::new();
+            for (key, value) in table.iter() {
+                fields.insert(key.to_string(), value_to_json(value)?);
+            }
+            Value::Object(fields)
+        }
+    };
+    Ok(value)
+}
+
+/// Converts a JSON object into a table of a TOML document.
+///
+/// A table which only contains other tables gets no header of its own, e.g. `[profiles]` is left
+/// out before `[profiles.coding]`.
+fn json_to_table(fields: &Map<String, Value>) -> Result<Table> {
+    let mut table = Table::new();
+    for (key, value) in fields {
+        let item = match value {
+            Value::Null => continue,
+            Value::Object(fields) => Item::Table(json_to_table(fields)?),
+            Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_object) => {
+                let mut tables = ArrayOfTables::new();
+                for fields in items.iter().filter_map(Value::as_object) {
+                    tables.push(json_to_table(fields)?);
+                }
+                Item::ArrayOfTables(tables)
+            }
+            value => {
+                let mut value = json_to_value(value)?;
+                if let toml_edit::Value::Array(items) = &mut value {
+                    put_tables_on_separate_lines(items);
+                }
+                Item::Value(value)
+            }
+        };
+        table.insert(key, item);
+    }
+    let has_values = table.iter().any(|(_, item)| item.is_value());
+    table.set_implicit(!has_values && !table.is_empty());
+    Ok(table)
+}
+
+/// Converts a JSON value into an inline value of a TOML document.
+fn json_to_value(value: &Value) -> Result<toml_edit::Value> {
+    let value = match value {
+        Value::Null => bail!("TOML has no null value, so it can not be written in an array."),
+        Value::Bool(boolean) => (*boolean).into(),
+        Value::Number(number) => match (number.as_i64(), number.as_f64()) {
+            (Some(integer), _) => integer.into(),
+            (None, Some(float)) if !number.is_u64() => float.into(),
+            _ => bail!("The number {} is too large for TOML.", number),
+        },
+        Value::String(string) => string.as_str().into(),
+        Value::Array(items) => {
+            let mut array = Array::new();
+            for item in items {
+                array.push(json_to_value(item)?);
+            }
+            toml_edit::Value::Array(array)
+        }
+        Value::Object(fields) => {
+            let mut table = InlineTable::new();
+            for (key, value) in fields.iter().filter(|(_, value)| !value.is_null()) {
+                table.insert(key, json_to_value(value)?);
+            }
+            toml_edit::Value::InlineTable(table)
+        }
+    };
+    Ok(value)
+}
+
+/// Puts every item of an array containing inline tables on its own line, so e.g. a sequence of
+/// end events stays readable.
+fn put_tables_on_separate_lines(items: &mut Array) {
+    if !items.iter().any(toml_edit::Value::is_inline_table) {
+        return;
+    }
+    for item in items.iter_mut() {
+        item.decor_mut().set_prefix("\n    ");
+    }
+    items.set_trailing("\n");
+    items.set_trailing_comma(true);
+}
+
+#[test]
+fn test_format_toml() {
+    let value = serde_json::json!({
+        "durationPomodoro": 25,
+        "httpPort": null,
+        "endEventPomodoro": {
+            "sequence": [
+                { "sound": { "builtin": "bell" } },
+                { "delay": { "seconds": 30 } },
+                "lockScreen"
+            ]
+        },
+        "timedEvents": [
+            { "at": { "fraction": 0.5 }, "event": "lockScreen" }
+        ],
+        "profiles": { "coding": { "durationPomodoro": 50 }, "empty": {} }
+    });
+    let contents = format_toml(&value).unwrap();
+    assert!(contents.contains(
+        "sequence = [\n    { sound = { builtin = \"bell\" } },\n    { delay = { seconds = 30 } },\n    \"lockScreen\",\n]"
+    ));
+    assert!(contents.contains("[[timedEvents]]"));
+    assert!(!contents.contains("[profiles]\n"));
+    assert!(contents.contains("[profiles.empty]"));
+    assert!(!contents.contains("httpPort"));
+
+    let mut expected = value;
+    expected.as_object_mut().unwrap().remove("httpPort");
+    assert_eq!(parse_toml(&contents).unwrap(), expected);
+
+    assert!(format_toml(&serde_json::json!({ "args": ["a", null] })).is_err());
+    assert!(format_toml(&serde_json::json!({ "port": u64::MAX })).is_err());
+}
@@ -0,0 +1,505 @@
+//! This module contains the `TuiView` which shows a running session as a full-screen interface.
+//!
+//! Instead of the progress bar and the scrolling messages of the `TerminalView`, the interface
+//! shows the remaining time in big digits, the phase, a coloured progress gauge, the Pomodoros of
+//! the current cycle, the Pomodoros completed today and the keys. It is drawn with `ratatui` on
+//! the alternate screen, so the previous content of the terminal is restored when it is closed.
+use crate::history::{get_filepath_history, read_records, HistoryRecord};
+use crate::key_bindings::{Action, Key, KeyBindings};
+use crate::pomodoro_options::PomodoroOptions;
+use crate::session::{Phase, PhaseEvent};
+use crate::timer::Timer;
+use crate::view::View;
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate};
+use crossterm::cursor::{Hide, Show};
+use crossterm::execute;
+use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::{Alignment, Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Gauge, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+use std::collections::VecDeque;
+use std::io::stdout;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// The number of messages shown below the information about the phase.
+const MESSAGE_LINES: usize = 4;
+
+/// Shows a running session as a full-screen interface on the terminal.
+///
+/// The interface is redrawn on a separate thread whenever the shown state changes, i.e. at most
+/// once per second while a timer runs.
+pub struct TuiView {
+    state: Arc<Mutex<TuiState>>,
+    redraw: Mutex<Option<Sender<()>>>,
+    renderer: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// The state shown by the `TuiView`.
+#[derive(Debug, Default)]
+struct TuiState {
+    /// The running or last phase, or `None` before the first and after the last phase.
+    phase: Option<Phase>,
+    /// The number of Pomodoros completed in this session.
+    counter: i32,
+    /// The interval in number of Pomodoros after which a long break is taken.
+    interval_long_break: i32,
+    /// The number of Pomodoros completed today, including earlier sessions.
+    completed_today: usize,
+    /// The planned duration of the phase.
+    duration: Duration,
+    /// The elapsed time of the phase.
+    elapsed_time: Duration,
+    /// Whether the timer of the phase is paused.
+    paused: bool,
+    /// The information about the phase created by the `message_creator`.
+    phase_info: String,
+    /// The latest messages, starting with the oldest one.
+    messages: VecDeque<String>,
+    /// The text typed by the user, if the user is asked for input.
+    input: Option<String>,
+    /// The keys shown in the footer.
+    key_help: String,
+}
+
+impl TuiView {
+    /// Switches the terminal to the alternate screen and starts drawing the interface.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The Pomodoro options, used for the cycle and the history of today.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the terminal does not support the interface.
+    pub fn start(options: &PomodoroOptions) -> Result<Self> {
+        execute!(stdout(), EnterAlternateScreen, Hide)
+            .context("Failed to enter the alternate screen.")?;
+        let terminal = match Terminal::new(CrosstermBackend::new(stdout())) {
+            Ok(terminal) => terminal,
+            Err(e) => {
+                let _ = execute!(stdout(), LeaveAlternateScreen, Show);
+                return Err(e).context("Failed to create the terminal interface.");
+            }
+        };
+        let state = Arc::new(Mutex::new(TuiState {
+            interval_long_break: options.interval_long_break,
+            completed_today: completed_pomodoros_today(options),
+            key_help: key_help(&options.key_bindings),
+            ..TuiState::default()
+        }));
+        let (sender, receiver) = channel();
+        let renderer_state = state.clone();
+        let renderer = thread::spawn(move || render(terminal, renderer_state, receiver));
+        let view = TuiView {
+            state,
+            redraw: Mutex::new(Some(sender)),
+            renderer: Mutex::new(Some(renderer)),
+        };
+        view.request_redraw();
+        Ok(view)
+    }
+
+    /// Updates the phase, the cycle and the Pomodoros completed today from the event of the
+    /// session. It is registered with `PomodoroSession::on_phase_event`.
+    pub fn on_phase_event(&self, event: &PhaseEvent) {
+        {
+            let mut state = self.state.lock().unwrap();
+            match *event {
+                PhaseEvent::Started {
+                    phase,
+                    duration,
+                    counter,
+                } => {
+                    state.phase = Some(phase);
+                    state.duration = duration;
+                    state.elapsed_time = Duration::ZERO;
+                    state.counter = counter;
+                }
+                PhaseEvent::Finished { phase, counter } => {
+                    if phase == Phase::Pomodoro {
+                        state.completed_today += 1;
+                    }
+                    state.counter = counter;
+                }
+                PhaseEvent::Quit { counter, .. } => state.counter = counter,
+                PhaseEvent::SessionEnded { counter } => {
+                    state.phase = None;
+                    state.counter = counter;
+                }
+            }
+        }
+        self.request_redraw();
+    }
+
+    /// Asks the render thread to draw the interface, unless the view is closed.
+    fn request_redraw(&self) {
+        if let Some(sender) = self.redraw.lock().unwrap().as_ref() {
+            let _ = sender.send(());
+        }
+    }
+
+    /// Updates the progress from the timer and redraws the interface if the shown time changed.
+    fn set_progress(&self, timer: &Timer, force_redraw: bool) {
+        let changed = {
+            let mut state = self.state.lock().unwrap();
+            let shown_before = (state.remaining_seconds(), state.paused);
+            state.duration = timer.get_duration();
+            state.elapsed_time = timer.get_elapsed_time();
+            state.paused = timer.is_paused();
+            shown_before != (state.remaining_seconds(), state.paused)
+        };
+        if changed || force_redraw {
+            self.request_redraw();
+        }
+    }
+}
+
+impl View for TuiView {
+    fn show_phase_info(&self, info: &str) {
+        self.state.lock().unwrap().phase_info = info.to_string();
+        self.request_redraw();
+    }
+
+    fn show_message(&self, message: &str) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.messages.push_back(message.to_string());
+            while state.messages.len() > MESSAGE_LINES {
+                state.messages.pop_front();
+            }
+        }
+        self.request_redraw();
+    }
+
+    fn show_input(&self, input: Option<&str>) {
+        self.state.lock().unwrap().input = input.map(str::to_string);
+        self.request_redraw();
+    }
+
+    fn start_progress(&self, timer: &Timer) {
+        self.set_progress(timer, true);
+    }
+
+    fn update_progress(&self, timer: &Timer) {
+        self.set_progress(timer, false);
+    }
+
+    fn reset_progress(&self, timer: &Timer) {
+        self.set_progress(timer, true);
+    }
+
+    fn finish_progress(&self) {}
+
+    fn close(&self) {
+        // Dropping the sender ends the render thread.
+        self.redraw.lock().unwrap().take();
+        if let Some(renderer) = self.renderer.lock().unwrap().take() {
+            let _ = renderer.join();
+            if let Err(e) = execute!(stdout(), LeaveAlternateScreen, Show) {
+                log::error!("Failed to leave the alternate screen: {:#}", e);
+            }
+        }
+    }
+}
+
+impl Drop for TuiView {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+impl TuiState {
+    /// Gets the remaining time of the phase in seconds, rounded up like a countdown.
+    fn remaining_seconds(&self) -> u64 {
+        let remaining_time = self.duration.saturating_sub(self.elapsed_time);
+        remaining_time.as_millis().div_ceil(1000) as u64
+    }
+
+    /// Gets the number of Pomodoros of the current cycle which are completed.
+    fn completed_in_cycle(&self) -> i32 {
+        let interval = self.interval_long_break.max(1);
+        // The counter is increased when the break after a Pomodoro is over.
+        match self.phase {
+            Some(Phase::ShortBreak | Phase::LongBreak) => self.counter % interval + 1,
+            _ => self.counter % interval,
+        }
+    }
+}
+
+/// Draws the interface whenever a redraw is requested until the view is closed.
+fn render<B: Backend>(
+    mut terminal: Terminal<B>,
+    state: Arc<Mutex<TuiState>>,
+    redraw: Receiver<()>,
+) {
+    while redraw.recv().is_ok() {
+        // Requests which arrived in the meantime are handled by the same draw.
+        while redraw.try_recv().is_ok() {}
+        let state = state.lock().unwrap();
+        if let Err(e) = terminal.draw(|frame| draw(frame, &state)) {
+            log::error!("Failed to draw the interface: {:#}", e);
+        }
+    }
+}
+
+/// Draws the state on the frame.
+fn draw(frame: &mut Frame, state: &TuiState) {
+    let color = phase_color(state.phase);
+    let block = Block::bordered()
+        .title(" Locking Pomodoro Timer ")
+        .border_style(Style::default().fg(color));
+    let area = block.inner(frame.size());
+    frame.render_widget(block, frame.size());
+    let [phase_area, _, digits_area, _, gauge_area, cycle_area, _, info_area, messages_area, help_area] =
+        Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(5),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(2),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+
+    let mut phase_name = match state.phase {
+        Some(phase) => phase.to_string(),
+        None => "Waiting".to_string(),
+    };
+    if state.paused {
+        phase_name.push_str(" (paused)");
+    }
+    let phase_style = Style::default().fg(color).add_modifier(Modifier::BOLD);
+    frame.render_widget(
+        Paragraph::new(phase_name)
+            .style(phase_style)
+            .alignment(Alignment::Center),
+        phase_area,
+    );
+
+    let digits = big_digits(&format_countdown(state.remaining_seconds()));
+    frame.render_widget(
+        Paragraph::new(digits.into_iter().map(Line::from).collect::<Vec<_>>())
+            .style(Style::default().fg(color))
+            .alignment(Alignment::Center),
+        digits_area,
+    );
+
+    let ratio = if state.duration.is_zero() {
+        0.0
+    } else {
+        (state.elapsed_time.as_secs_f64() / state.duration.as_secs_f64()).clamp(0.0, 1.0)
+    };
+    frame.render_widget(
+        Gauge::default()
+            .gauge_style(Style::default().fg(color).bg(Color::Black))
+            .ratio(ratio),
+        gauge_area,
+    );
+
+    let completed = state.completed_in_cycle();
+    let dots: String = (0..state.interval_long_break)
+        .map(|index| if index < completed { "● " } else { "○ " })
+        .collect();
+    let completed_today = match state.completed_today {
+        1 => "1 Pomodoro".to_string(),
+        count => format!("{} Pomodoros", count),
+    };
+    frame.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled(dots, Style::default().fg(Color::Red)),
+            Span::raw(format!(" Today: {} completed", completed_today)),
+        ]))
+        .alignment(Alignment::Center),
+        cycle_area,
+    );
+
+    frame.render_widget(
+        Paragraph::new(state.phase_info.as_str())
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true }),
+        info_area,
+    );
+
+    let mut lines: Vec<Line> = state
+        .messages
+        .iter()
+        .map(|message| Line::from(message.as_str()))
+        .collect();
+    if let Some(input) = &state.input {
+        lines.push(Line::styled(
+            format!("> {}", input),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    frame.render_widget(
+        Paragraph::new(lines).wrap(Wrap { trim: false }),
+        messages_area,
+    );
+
+    frame.render_widget(
+        Paragraph::new(state.key_help.as_str())
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center),
+        help_area,
+    );
+}
+
+/// Generates the keys shown in the footer from the key bindings, e.g. `p pause | r resume`.
+fn key_help(key_bindings: &KeyBindings) -> String {
+    let skip = format!("skip {} min", key_bindings.skip_minutes);
+    let extend = format!("+{} min", key_bindings.extend_minutes);
+    let entries = [
+        (Action::TogglePause, "pause/resume"),
+        (Action::Pause, "pause"),
+        (Action::Resume, "resume"),
+        (Action::Skip(key_bindings.skip_minutes), skip.as_str()),
+        (Action::Extend(key_bindings.extend_minutes), extend.as_str()),
+        (Action::NextPhase, "next"),
+        (Action::ChangeLabel, "label"),
+        (Action::QuitPhase, "quit"),
+        (Action::Confirm, "confirm"),
+        (Action::QuitApp, "exit"),
+    ];
+    let parts: Vec<String> = entries
+        .iter()
+        .filter_map(|(action, description)| {
+            let keys = key_bindings.keys(action);
+            if keys.is_empty() {
+                return None;
+            }
+            let keys: Vec<String> = keys.iter().map(Key::to_string).collect();
+            Some(format!("{} {}", keys.join("/"), description))
+        })
+        .collect();
+    parts.join(" | ")
+}
+
+/// Gets the colour of the phase.
+fn phase_color(phase: Option<Phase>) -> Color {
+    match phase {
+        Some(Phase::Pomodoro) => Color::Red,
+        Some(Phase::AdditionalPomodoro) => Color::Magenta,
+        Some(Phase::ShortBreak) => Color::Green,
+        Some(Phase::LongBreak) => Color::Blue,
+        None => Color::Gray,
+    }
+}
+
+/// Formats the remaining seconds as `MM:SS`, or as `H:MM:SS` from one hour on.
+fn format_countdown(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
+
+/// Renders the digits and colons of the text in a font of five lines. Other characters are
+/// rendered as spaces.
+fn big_digits(text: &str) -> Vec<String> {
+    let mut lines = vec![String::new(); 5];
+    for (index, character) in text.chars().enumerate() {
+        let glyph: [&str; 5] = match character {
+            '0' => ["###", "# #", "# #", "# #", "###"],
+            '1' => ["  #", "  #", "  #", "  #", "  #"],
+            '2' => ["###", "  #", "###", "#  ", "###"],
+            '3' => ["###", "  #", "###", "  #", "###"],
+            '4' => ["# #", "# #", "###", "  #", "  #"],
+            '5' => ["###", "#  ", "###", "  #", "###"],
+            '6' => ["###", "#  ", "###", "# #", "###"],
+            '7' => ["###", "  #", "  #", "  #", "  #"],
+            '8' => ["###", "# #", "###", "# #", "###"],
+            '9' => ["###", "# #", "###", "  #", "###"],
+            ':' => [" ", "#", " ", "#", " "],
+            _ => ["   "; 5],
+        };
+        for (line, row) in lines.iter_mut().zip(glyph) {
+            if index > 0 {
+                line.push_str("  ");
+            }
+            // Every pixel is two characters wide, so the digits are not too narrow.
+            line.extend(
+                row.chars()
+                    .map(|pixel| if pixel == '#' { "██" } else { "  " }),
+            );
+        }
+    }
+    lines
+}
+
+/// Counts the Pomodoros completed today in the history.
+///
+/// Errors are logged, so that a broken history does not stop the timer.
+fn completed_pomodoros_today(options: &PomodoroOptions) -> usize {
+    let records = get_filepath_history(&options.filepath_history)
+        .and_then(|filepath_history| read_records(&filepath_history));
+    match records {
+        Ok(records) => count_completed_pomodoros(&records, Local::now().date_naive()),
+        Err(e) => {
+            log::error!("Failed to read the history: {:#}", e);
+            0
+        }
+    }
+}
+
+/// Counts the Pomodoros which started on the day and were not quit.
+fn count_completed_pomodoros(records: &[HistoryRecord], day: NaiveDate) -> usize {
+    records
+        .iter()
+        .filter(|record| {
+            record.phase == Phase::Pomodoro && !record.quit && record.start.date_naive() == day
+        })
+        .count()
+}
+
+#[test]
+fn test_big_digits() {
+    assert_eq!(format_countdown(25 * 60), "25:00");
+    assert_eq!(format_countdown(3600 + 61), "1:01:01");
+    let lines = big_digits("1:0");
+    assert_eq!(lines[0], "    ██      ██████");
+    assert_eq!(lines[1], "    ██  ██  ██  ██");
+    assert!(lines.iter().all(|line| line.chars().count() == 18));
+}
+
+#[test]
+fn test_draw() {
+    use ratatui::backend::TestBackend;
+
+    let state = TuiState {
+        phase: Some(Phase::ShortBreak),
+        counter: 1,
+        interval_long_break: 4,
+        completed_today: 3,
+        duration: Duration::from_secs(5 * 60),
+        elapsed_time: Duration::from_millis(60_500),
+        messages: VecDeque::from(["Timer paused.".to_string()]),
+        input: Some("cod".to_string()),
+        key_help: key_help(&KeyBindings::default()),
+        ..TuiState::default()
+    };
+    assert_eq!(state.remaining_seconds(), 4 * 60);
+    assert_eq!(state.completed_in_cycle(), 2);
+    let mut terminal = Terminal::new(TestBackend::new(90, 24)).unwrap();
+    terminal.draw(|frame| draw(frame, &state)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let text: String = buffer.content.iter().map(|cell| cell.symbol()).collect();
+    assert!(text.contains("Short break"));
+    assert!(text.contains("● ● ○ ○"));
+    assert!(text.contains("Today: 3 Pomodoros completed"));
+    assert!(text.contains("Timer paused."));
+    assert!(text.contains("> cod"));
+    assert!(text.contains("space pause/resume | p pause | r resume | s skip 1 min | e +5 min"));
+}
@@ -0,0 +1,108 @@
+//! This module contains the views which show a running session to the user.
+//!
+//! The `TerminalView` prints messages line by line and shows the progress of a phase with a
+//! progress bar. The full-screen interface of the `tui` module is another view.
+use crate::timer::Timer;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::Write;
+use std::sync::Mutex;
+
+/// Shows the messages, typed input and progress of a running session.
+pub trait View: Send + Sync {
+    /// Shows the information about the phase which starts, e.g. the upcoming phase and the
+    /// Pomodoros till the long break.
+    fn show_phase_info(&self, info: &str);
+
+    /// Shows a message, e.g. a question or the confirmation of a key.
+    fn show_message(&self, message: &str);
+
+    /// Shows the text typed by the user, e.g. the task label, or `None` when the input is done.
+    fn show_input(&self, input: Option<&str>);
+
+    /// Starts showing the progress of the timer, which has just been created.
+    fn start_progress(&self, timer: &Timer);
+
+    /// Updates the progress of the timer. It is called at least every second while it runs.
+    fn update_progress(&self, timer: &Timer);
+
+    /// Updates the progress after the timer was resumed, skipped or extended, so the estimated
+    /// remaining time is calculated anew.
+    fn reset_progress(&self, timer: &Timer) {
+        self.update_progress(timer);
+    }
+
+    /// Stops showing the progress of the timer.
+    fn finish_progress(&self);
+
+    /// Restores the terminal, e.g. before the program exits.
+    fn close(&self) {}
+}
+
+/// Shows the session line by line on the terminal with a progress bar for the running phase.
+#[derive(Default)]
+pub struct TerminalView {
+    bar: Mutex<Option<ProgressBar>>,
+}
+
+impl View for TerminalView {
+    fn show_phase_info(&self, info: &str) {
+        println!("{}", info);
+    }
+
+    fn show_message(&self, message: &str) {
+        println!("{}", message);
+    }
+
+    fn show_input(&self, input: Option<&str>) {
+        // While a phase is running, the typed text is shown next to the progress bar, so it is
+        // not overwritten when the bar is drawn again.
+        if let Some(bar) = self.bar.lock().unwrap().as_ref() {
+            bar.set_message(
+                input
+                    .map(|input| format!("> {}", input))
+                    .unwrap_or_default(),
+            );
+            return;
+        }
+        match input {
+            // The typed characters are echoed manually, because the terminal is in raw mode.
+            Some(input) => print!("\r\x1b[2K{}", input),
+            None => println!(),
+        }
+        let _ = std::io::stdout().flush();
+    }
+
+    fn start_progress(&self, timer: &Timer) {
+        // The progress is measured in milliseconds, so the bar moves smoothly.
+        let bar = ProgressBar::new(timer.get_duration().as_millis() as u64);
+        bar.set_style(
+            ProgressStyle::with_template("[{elapsed}/{eta}] {wide_bar:.cyan/blue} {msg}").unwrap(),
+        );
+        bar.set_position(timer.get_elapsed_time().as_millis() as u64);
+        *self.bar.lock().unwrap() = Some(bar);
+    }
+
+    fn update_progress(&self, timer: &Timer) {
+        if let Some(bar) = self.bar.lock().unwrap().as_ref() {
+            bar.set_position(timer.get_elapsed_time().as_millis() as u64);
+        }
+    }
+
+    fn reset_progress(&self, timer: &Timer) {
+        let mut bar = self.bar.lock().unwrap();
+        if let Some(old_bar) = bar.take() {
+            let new_bar = old_bar.with_elapsed(timer.get_elapsed_time());
+            // The duration changes when the timer is extended.
+            new_bar.set_length(timer.get_duration().as_millis() as u64);
+            new_bar.set_position(timer.get_elapsed_time().as_millis() as u64);
+            new_bar.reset_eta();
+            *bar = Some(new_bar);
+        }
+    }
+
+    fn finish_progress(&self) {
+        if let Some(bar) = self.bar.lock().unwrap().take() {
+            bar.finish();
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! This module contains the HTTP requests sent by the `webhook` end event.
+//!
+//! A webhook describes the phase transition as JSON, e.g. for a local aggregator of the Pomodoros
+//! of a team. Every attempt has a short timeout and failed attempts are retried with an increasing
+//! delay. The requests are sent in a background thread, so a slow endpoint never delays the next
+//! phase.
+use crate::end_events::EndEventContext;
+use crate::session::Phase;
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Local};
+use log::{debug, warn};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::Duration;
+
+/// The number of attempts to send a webhook.
+const ATTEMPTS: u32 = 3;
+/// The delay before the second attempt. It is doubled for every further attempt.
+const BACKOFF: Duration = Duration::from_secs(1);
+/// The time after which a single attempt is given up.
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Represents the default JSON payload of a webhook.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WebhookPayload<'a> {
+    phase: Phase,
+    next_phase: Phase,
+    counter: i32,
+    pomodoros_till_long_break: i32,
+    break_minutes: u64,
+    label: Option<&'a str>,
+    time: DateTime<Local>,
+}
+
+/// Creates the default JSON payload describing the phase transition.
+///
+/// The payload contains the `phase`, `nextPhase`, `counter`, `pomodorosTillLongBreak`,
+/// `breakMinutes`, `label` and the current `time`.
+pub fn create_payload(context: &EndEventContext) -> String {
+    let payload = WebhookPayload {
+        phase: context.phase,
+        next_phase: context.next_phase,
+        counter: context.counter,
+        pomodoros_till_long_break: context.pomodoros_till_long_break(),
+        break_minutes: context.pomo_info.break_duration.as_secs() / 60,
+        label: context.label.as_deref(),
+        time: Local::now(),
+    };
+    serde_json::to_string(&payload).expect("Failed to serialize webhook payload.")
+}
+
+/// Sends the webhook, retrying failed attempts with an increasing delay.
+///
+/// Responses with a server error status are retried, responses with a client error status are not,
+/// because sending the same request again would fail again.
+///
+/// # Arguments
+///
+/// * `url` - The URL of the endpoint.
+/// * `method` - The HTTP method, e.g. `POST`.
+/// * `headers` - Additional headers. `Content-Type` is `application/json` if the body is JSON,
+///   unless it is set here. Other bodies, e.g. of a custom template, are sent without it.
+/// * `body` - The body of the request.
+///
+/// # Errors
+///
+/// Returns the error of the last attempt if no attempt succeeded.
+pub fn send_webhook(
+    url: &str,
+    method: &str,
+    headers: &BTreeMap<String, String>,
+    body: &str,
+) -> Result<()> {
+    send_with_retries(url, method, headers, body, ATTEMPTS, BACKOFF, TIMEOUT)
+}
+
+/// Sends the webhook with the given number of attempts, backoff and timeout.
+fn send_with_retries(
+    url: &str,
+    method: &str,
+    headers: &BTreeMap<String, String>,
+    body: &str,
+    attempts: u32,
+    backoff: Duration,
+    timeout: Duration,
+) -> Result<()> {
+    let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+    let set_json_content_type = !headers
+        .keys()
+        .any(|name| name.eq_ignore_ascii_case("content-type"))
+        && serde_json::from_str::<serde::de::IgnoredAny>(body).is_ok();
+    let mut delay = backoff;
+    for attempt in 1..=attempts {
+        let mut request = agent.request(method, url);
+        if set_json_content_type {
+            request = request.set("Content-Type", "application/json");
+        }
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        let error = match request.send_string(body) {
+            Ok(response) => {
+                debug!("Webhook {} {} answered {}.", method, url, response.status());
+                return Ok(());
+            }
+            Err(ureq::Error::Status(status, _)) if status < 500 => {
+                bail!("{} {} answered with status {}.", method, url, status)
+            }
+            Err(ureq::Error::Status(status, _)) => {
+                anyhow!("{} {} answered with status {}.", method, url, status)
+            }
+            Err(e) => anyhow!("{} {} failed: {}", method, url, e),
+        };
+        if attempt == attempts {
+            return Err(error.context(format!("Gave up after {} attempts.", attempts)));
+        }
+        warn!(
+            "Attempt {} of the webhook failed, retrying in {:?}: {:#}",
+            attempt, delay, error
+        );
+        thread::sleep(delay);
+        delay *= 2;
+    }
+    bail!("The webhook was not sent, because no attempt is allowed.")
+}
+
+#[test]
+fn test_webhook_is_retried() {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/pomodoro", server.server_addr().to_ip().unwrap());
+    let endpoint = thread::spawn(move || {
+        let mut requests = Vec::new();
+        for status_code in [503, 200, 404] {
+            let mut request = server.recv().unwrap();
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let header = |name: &'static str| {
+                request
+                    .headers()
+                    .iter()
+                    .find(|header| header.field.equiv(name))
+                    .map(|header| header.value.to_string())
+            };
+            requests.push((
+                request.method().to_string(),
+                header("Content-Type"),
+                header("X-Team"),
+                body,
+            ));
+            request
+                .respond(tiny_http::Response::empty(status_code))
+                .unwrap();
+        }
+        requests
+    });
+
+    let headers = BTreeMap::from([("X-Team".to_string(), "blue".to_string())]);
+    let backoff = Duration::from_millis(10);
+    let timeout = Duration::from_secs(1);
+    send_with_retries(&url, "POST", &headers, "{}", 3, backoff, timeout).unwrap();
+    assert!(send_with_retries(&url, "PUT", &headers, "done", 3, backoff, timeout).is_err());
+
+    let requests = endpoint.join().unwrap();
+    assert_eq!(requests.len(), 3);
+    for (method, _, team, _) in &requests {
+        assert!(method == "POST" || method == "PUT");
+        assert_eq!(team.as_deref(), Some("blue"));
+    }
+    assert_eq!(requests[0].1.as_deref(), Some("application/json"));
+    assert_eq!(requests[1].1.as_deref(), Some("application/json"));
+    assert_eq!(requests[1].3, "{}");
+    // The client error is not retried and the body is not declared as JSON.
+    assert_eq!(requests[2].0, "PUT");
+    assert_eq!(requests[2].1, None);
+    assert_eq!(requests[2].3, "done");
+}